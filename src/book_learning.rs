@@ -0,0 +1,219 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use chess::Board;
+use crate::consts::{LEARNING_HASH_SIZE, LEARNING_QUEUE_SIZE};
+use crate::game_history::GameHistory;
+use crate::move_database::MoveDatabase;
+use crate::opening_book::OpeningBook;
+use crate::search::search_position;
+use crate::search_limits::SearchLimits;
+use crate::search_options::SearchOptions;
+use crate::search_signals::SearchSignals;
+use crate::transposition_table::TranspostionTable;
+
+/// A position played in the opening, to be searched again to `depth`.
+/// An interrupted search goes on after `completed_depth`.
+struct LearningJob
+{
+    board: Board,
+    plies: i32,
+    depth: u8,
+    completed_depth: u8,
+    options: SearchOptions
+}
+
+struct LearnerState
+{
+    jobs: VecDeque<LearningJob>,
+    idle: bool
+}
+
+impl LearnerState
+{
+    /// Queues `job` unless the queue is full. A position already waiting is
+    /// searched once, to the deeper of both depths.
+    fn push(&mut self, job: LearningJob) -> bool
+    {
+        if let Some(queued) = self.jobs.iter_mut().find(|queued| queued.board == job.board)
+        {
+            queued.depth = queued.depth.max(job.depth);
+            return false;
+        }
+
+        if self.jobs.len() >= LEARNING_QUEUE_SIZE
+        {
+            return false;
+        }
+
+        self.jobs.push_back(job);
+        true
+    }
+}
+
+struct LearnerShared
+{
+    state: Mutex<LearnerState>,
+    wake: Condvar,
+    signals: Arc<SearchSignals>
+}
+
+/// Fills the book with the engine's own results: positions played in the
+/// opening are searched again, deeper than the game did, on a background
+/// thread, and the best move is stored. It only runs while the engine is
+/// idle, a search interrupted by the next `go` goes on later from its last
+/// completed depth. With its own table, the result for a position does not
+/// depend on the game around it.
+#[derive(Clone)]
+pub struct BookLearner
+{
+    shared: Arc<LearnerShared>
+}
+
+impl BookLearner
+{
+    pub fn start(db: Arc<Mutex<MoveDatabase>>) -> BookLearner
+    {
+        let shared = Arc::new(LearnerShared
+        {
+            state: Mutex::new(LearnerState { jobs: VecDeque::new(), idle: true }),
+            wake: Condvar::new(),
+            signals: Arc::new(SearchSignals::new())
+        });
+
+        let learner = BookLearner { shared: shared.clone() };
+
+        thread::spawn(move ||
+        {
+            let transposition_table = TranspostionTable::new(LEARNING_HASH_SIZE);
+
+            loop
+            {
+                let job =
+                {
+                    let mut state = shared.state.lock().unwrap();
+
+                    while !state.idle || state.jobs.is_empty()
+                    {
+                        state = shared.wake.wait(state).unwrap();
+                    }
+
+                    // Reset under the lock, so that a `pause` can't be lost
+                    shared.signals.reset(false);
+                    state.jobs.pop_front().unwrap()
+                };
+
+                if db.lock().unwrap().is_read_only()
+                {
+                    continue;
+                }
+
+                // An interrupted job is the next one to run again, so the
+                // table still holds its earlier iterations
+                if job.completed_depth == 0
+                {
+                    transposition_table.clear();
+                }
+
+                let limits = SearchLimits
+                {
+                    depth: Some(job.depth),
+                    start_depth: Some(job.completed_depth + 1),
+                    ..SearchLimits::default()
+                };
+
+                // Book moves are stored per position, so the game that led here is left out
+                let history = GameHistory::new(&job.board, 0, job.plies);
+                let search_move = search_position(&job.board, &history, &transposition_table, &limits, &job.options, shared.signals.clone());
+                let completed_depth = job.completed_depth.max(search_move.depth());
+
+                if shared.signals.is_stopped() && completed_depth < job.depth
+                {
+                    shared.state.lock().unwrap().jobs.push_front(LearningJob { completed_depth, ..job });
+                }
+                else if let Some(mov) = search_move.mov()
+                {
+                    db.lock().unwrap().add_move(&job.board, mov);
+                }
+            }
+        });
+
+        learner
+    }
+
+    /// Queues `board` for a search to `depth` plies with `options`, returns
+    /// immediately.
+    pub fn schedule(&self, board: Board, plies: i32, depth: u8, options: SearchOptions)
+    {
+        let options = SearchOptions { print_info: false, ..options };
+        let job = LearningJob { board, plies, depth, completed_depth: 0, options };
+
+        if self.shared.state.lock().unwrap().push(job)
+        {
+            self.shared.wake.notify_one();
+        }
+    }
+
+    /// Stops learning while the engine searches for the game.
+    pub fn pause(&self)
+    {
+        let mut state = self.shared.state.lock().unwrap();
+
+        state.idle = false;
+        self.shared.signals.stop();
+    }
+
+    /// Lets learning run again once the engine is idle.
+    pub fn resume(&self)
+    {
+        self.shared.state.lock().unwrap().idle = true;
+        self.shared.wake.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use chess::{ChessMove, MoveGen};
+    use super::*;
+
+    fn job(board: Board, depth: u8) -> LearningJob
+    {
+        LearningJob { board, plies: 0, depth, completed_depth: 0, options: SearchOptions::default() }
+    }
+
+    #[test]
+    fn queued_position_is_searched_once_to_the_deeper_depth()
+    {
+        let mut state = LearnerState { jobs: VecDeque::new(), idle: true };
+        let after_e4 = Board::default().make_move_new(ChessMove::from_str("e2e4").unwrap());
+
+        assert!(state.push(job(Board::default(), 6)));
+        assert!(state.push(job(after_e4, 6)));
+        assert!(!state.push(job(Board::default(), 8)));
+        assert!(!state.push(job(after_e4, 4)));
+
+        assert_eq!(state.jobs.iter().map(|job| job.depth).collect::<Vec<u8>>(), [8, 6]);
+    }
+
+    #[test]
+    fn queue_is_capped()
+    {
+        let mut state = LearnerState { jobs: VecDeque::new(), idle: true };
+        let mut boards = vec![Board::default()];
+
+        // Two plies from the start give 400 different positions
+        for _ in 0..2
+        {
+            boards = boards.iter().flat_map(|board| MoveGen::new_legal(board).map(|mov| board.make_move_new(mov))).collect();
+        }
+
+        for board in boards
+        {
+            state.push(job(board, 6));
+        }
+
+        assert_eq!(state.jobs.len(), LEARNING_QUEUE_SIZE);
+    }
+}
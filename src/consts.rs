@@ -1,21 +1,31 @@
-pub const ENDGAME_KING_DISTANCE: f32 = 0.2134;
-pub const OPENING_QUEEN_SAFETY: f32 = 0.9;
-pub const ROOK_ON_7TH_RANK_BONUS: f32 = 0.87;
-pub const KING_MOVED_NOT_ENDGAME: f32 = 6.0;
-pub const PAWN_SHIELD_SCORE: f32 = 0.4;
-pub const GOOD_KNIGHT: f32 = 0.2;
+use crate::score::Score;
+
+pub const ENDGAME_KING_DISTANCE: Score = Score::new(0.0, 0.05);
+pub const OPENING_QUEEN_SAFETY: Score = Score::new(0.9, 0.0);
+pub const ROOK_ON_7TH_RANK_BONUS: Score = Score::splat(0.87);
+pub const KING_MOVED_NOT_ENDGAME: Score = Score::new(6.0, 0.0);
+pub const PAWN_SHIELD_SCORE: Score = Score::new(0.4, 0.0);
+pub const QUEEN_NEAR_KING_BONUS: Score = Score::new(2.6, 0.0);
+pub const QUEEN_CLOSE_TO_KING_BONUS: Score = Score::new(0.8, 0.0);
+pub const KING_PAWN_DISTANCE: Score = Score::new(0.0, 0.1);
 
-pub const OPENING_PIECES_FOR_KING_SAFETY: u8 = 21;
-pub const OPENING_PIECES_FOR_PIECE_SAFETY: i32 = 27;
 pub const MAX_PIECE_FOR_ENDGAME: u8 = 7;
 pub const ENDGAME_PIECE_FOR_GREATER_DEPTH: u8 = 12;
-pub const CONTROLLING_SQUARE: f32 = 0.02;
-pub const CONTROLLING_SQUARE_OPENING: f32 = 0.03;
-pub const DEFENDING_PIECE: f32 = 0.05;
-pub const DEFENDING_PIECE_OPENING: f32 = 0.04;
-pub const PAWN_CHAIN_BONUS: f32 = 0.09;
-pub const PAWN_ON_SAFE_FILE_DISADVANTAGE: f32 = 0.18;
-pub const ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE: f32 = 0.2;
+pub const CONTROLLING_SQUARE: Score = Score::new(0.03, 0.02);
+pub const DEFENDING_PIECE: Score = Score::new(0.04, 0.05);
+pub const DOUBLED_PAWN: Score = Score::new(0.1, 0.18);
+pub const ISOLATED_PAWN: Score = Score::new(0.1, 0.15);
+pub const BACKWARD_PAWN: Score = Score::new(0.08, 0.1);
+pub const CONNECTED_PAWN: Score = Score::splat(0.09);
+pub const PASSED_PAWN: Score = Score::new(0.02, 0.04);
+pub const PASSED_PAWN_FREE_PATH: Score = Score::new(0.0, 0.03);
+pub const ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE: Score = Score::new(0.0, 0.2);
+
+/// Game phase weight of each piece type, in `Piece::to_index` order.
+pub const PIECE_PHASE: [i32; 6] = [0, 1, 1, 2, 4, 0];
+pub const TOTAL_PHASE: i32 = 24;
+pub const SEE_KING_VALUE: f32 = 100.0;
+pub const MAX_HISTORY: i32 = 16384;
 
 pub const OPENING_FOR_DIFF_EVAL: i32 = 15;
 pub const OPENING_BOOK_MAX_PLIES: i32 = 12;
@@ -23,4 +33,47 @@ pub const OPENING_SEARCH_DEPTH: u8 = 4;
 pub const SEARCH_DEPTH: u8 = 5;
 pub const ENDGAME_SEARCH_DEPTH: u8 = 7;
 pub const USE_QUIESCENSE_SEARCH_AFTER_NPLIES: i32 = 10;
- 
\ No newline at end of file
+pub const QUIESCENCE_DEPTH: u8 = 4;
+pub const MAX_SEARCH_DEPTH: u8 = 64;
+pub const NODE_FLUSH_INTERVAL: u64 = 1024;
+pub const SWING_EXTENSION: f32 = 1.5;
+pub const SWING_REDUCTION: f32 = 1.0;
+pub const NULL_WINDOW: f32 = 0.01;
+pub const ASPIRATION_MIN_DEPTH: u8 = 3;
+pub const ASPIRATION_WINDOW: f32 = 0.25;
+pub const ASPIRATION_MAX_WINDOW: f32 = 4.0;
+pub const NULL_MOVE_MIN_DEPTH: u8 = 3;
+pub const NULL_MOVE_REDUCTION: u8 = 3;
+pub const NULL_MOVE_VERIFICATION_PIECES: u32 = 2;
+pub const REVERSE_FUTILITY_DEPTH: u8 = 6;
+pub const REVERSE_FUTILITY_MARGIN: f32 = 1.0;
+pub const FUTILITY_MARGIN: f32 = 1.5;
+pub const RAZORING_DEPTH: u8 = 2;
+pub const RAZORING_MARGIN: f32 = 3.0;
+pub const LATE_MOVE_PRUNING_DEPTH: u8 = 3;
+pub const LATE_MOVE_PRUNING_MOVES: usize = 4;
+pub const LMR_MIN_DEPTH: u8 = 3;
+pub const LMR_MIN_MOVES: usize = 3;
+
+pub const DEFAULT_MOVES_TO_GO: u64 = 30;
+pub const MAX_TIME_FACTOR: u64 = 3;
+pub const MIN_THINKING_TIME: u64 = 10;
+pub const MIN_THINKING_SHARE: u64 = 20;
+
+pub const MATE_SCORE: f32 = 10000.0;
+pub const MATE_THRESHOLD: f32 = MATE_SCORE - 256.0;
+pub const TABLEBASE_WIN_SCORE: f32 = 200.0;
+pub const LEARNING_HASH_SIZE: usize = 16;
+pub const LEARNING_QUEUE_SIZE: usize = 64;
+pub const BENCH_HASH_SIZE: usize = 16;
+pub const BENCH_DEPTH: u8 = 3;
+pub const EPD_HASH_SIZE: usize = 64;
+pub const EPD_MOVETIME: u64 = 1000;
+pub const TUNE_STEP: f32 = 0.01;
+pub const TUNE_MAX_PASSES: u32 = 100;
+pub const TUNE_OUTPUT: &str = "tuned_params.toml";
+pub const DATAGEN_HASH_SIZE: usize = 16;
+pub const DATAGEN_DEPTH: u8 = 3;
+pub const DATAGEN_RANDOM_PLIES: usize = 8;
+pub const DATAGEN_MAX_PLIES: i32 = 400;
+pub const PAWN_HASH_SIZE: usize = 8192;
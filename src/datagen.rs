@@ -0,0 +1,235 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+use chess::{Board, BoardStatus, ChessMove, Color, MoveGen, Piece, ALL_SQUARES};
+use rand::seq::IteratorRandom;
+use crate::consts::{DATAGEN_HASH_SIZE, DATAGEN_MAX_PLIES, DATAGEN_RANDOM_PLIES};
+use crate::eval::count_all_pieces;
+use crate::game_history::GameHistory;
+use crate::opening_book::NoBook;
+use crate::search::{engine, is_mate_score};
+use crate::search_limits::SearchLimits;
+use crate::search_options::SearchOptions;
+use crate::search_signals::SearchSignals;
+use crate::shakmaty_conversion::en_passant_square;
+use crate::transposition_table::TranspostionTable;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum DataFormat
+{
+    Text,
+    Binary
+}
+
+/// A position the engine played from, with its search score in white
+/// relative centipawns.
+struct DataRecord
+{
+    board: Board,
+    halfmove_clock: u32,
+    plies: i32,
+    score: i16
+}
+
+impl DataRecord
+{
+    /// `<fen> | <score> | <wdl>`, the result being 1.0, 0.5 or 0.0 for White.
+    fn write_text(&self, out: &mut impl Write, result: f32) -> io::Result<()>
+    {
+        let fields = self.board.to_string();
+        let position = fields.split_whitespace().take(4).collect::<Vec<&str>>().join(" ");
+
+        writeln!(
+            out,
+            "{} {} {} | {} | {:.1}",
+            position,
+            self.halfmove_clock,
+            self.plies / 2 + 1,
+            self.score,
+            result
+        )
+    }
+
+    /// The 32 byte marlinformat record: occupancy, one nibble per piece in
+    /// square order (6 marking a rook that can still castle, +8 for Black),
+    /// side to move and en passant square, halfmove clock, fullmove number,
+    /// score and result (0 Black win, 1 draw, 2 White win).
+    fn write_binary(&self, out: &mut impl Write, result: f32) -> io::Result<()>
+    {
+        let board = &self.board;
+        let mut pieces = [0u8; 16];
+        let mut count = 0;
+
+        for square in ALL_SQUARES
+        {
+            let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square))
+            else
+            {
+                continue;
+            };
+
+            let rights = board.castle_rights(color);
+            let back_rank = color.to_my_backrank();
+            let castling_rook = piece == Piece::Rook && square.get_rank() == back_rank
+                && ((rights.has_kingside() && square.get_file() == chess::File::H)
+                    || (rights.has_queenside() && square.get_file() == chess::File::A));
+
+            let code = if castling_rook { 6 } else { piece.to_index() as u8 } | if color == Color::Black { 8 } else { 0 };
+
+            pieces[count / 2] |= code << (4 * (count % 2));
+            count += 1;
+        }
+
+        let en_passant = en_passant_square(board).map_or(64, |square| square as u8);
+
+        let side_to_move = if board.side_to_move() == Color::Black { 0x80 } else { 0 };
+        let wdl = (result * 2.0).round() as u8;
+
+        out.write_all(&board.combined().0.to_le_bytes())?;
+        out.write_all(&pieces)?;
+        out.write_all(&[side_to_move | en_passant, self.halfmove_clock.min(255) as u8])?;
+        out.write_all(&((self.plies / 2 + 1) as u16).to_le_bytes())?;
+        out.write_all(&self.score.to_le_bytes())?;
+        out.write_all(&[wdl, 0])
+    }
+}
+
+/// Positions where the static evaluation is meaningful: no check to escape
+/// and no capture or promotion about to change the material.
+fn is_quiet(board: &Board, mov: ChessMove) -> bool
+{
+    let is_en_passant = board.piece_on(mov.get_source()) == Some(Piece::Pawn)
+        && mov.get_source().get_file() != mov.get_dest().get_file();
+
+    board.checkers().popcnt() == 0
+        && board.piece_on(mov.get_dest()).is_none()
+        && !is_en_passant
+        && mov.get_promotion().is_none()
+}
+
+/// A random opening of `DATAGEN_RANDOM_PLIES` plies, `None` if the game
+/// ended on the way.
+fn random_opening() -> Option<(Board, GameHistory)>
+{
+    let mut rng = rand::thread_rng();
+    let mut board = Board::default();
+    let mut history = GameHistory::new(&board, 0, 0);
+
+    for _ in 0..DATAGEN_RANDOM_PLIES
+    {
+        let mov = MoveGen::new_legal(&board).choose(&mut rng)?;
+        let next_board = board.make_move_new(mov);
+
+        history.push(&board, mov, &next_board);
+        board = next_board;
+    }
+
+    if board.status() == BoardStatus::Ongoing { Some((board, history)) } else { None }
+}
+
+/// Plays one game from a random opening and returns its quiet positions
+/// and White's result.
+fn play_game(transposition_table: &TranspostionTable, limits: &SearchLimits, options: &SearchOptions) -> (Vec<DataRecord>, f32)
+{
+    let (mut board, mut history) = loop
+    {
+        if let Some(opening) = random_opening()
+        {
+            break opening;
+        }
+    };
+
+    let mut records = Vec::new();
+    transposition_table.clear();
+
+    let result = loop
+    {
+        match board.status()
+        {
+            BoardStatus::Checkmate => break if board.side_to_move() == Color::White { 0.0 } else { 1.0 },
+            BoardStatus::Stalemate => break 0.5,
+            BoardStatus::Ongoing => {}
+        }
+
+        let is_draw = history.is_repetition(history.plies())
+            || history.is_fifty_move_draw()
+            || count_all_pieces(&board) == 2
+            || history.plies() >= DATAGEN_MAX_PLIES;
+
+        if is_draw
+        {
+            break 0.5;
+        }
+
+        let search_move = engine(
+            &board,
+            &history,
+            &mut NoBook,
+            transposition_table,
+            limits,
+            options,
+            Arc::new(SearchSignals::new())
+        );
+
+        let mov = search_move.mov().expect("Ongoing game without a move");
+
+        if is_quiet(&board, mov) && !is_mate_score(search_move.eval())
+        {
+            records.push(DataRecord
+            {
+                board,
+                halfmove_clock: history.halfmove_clock(),
+                plies: history.plies(),
+                score: (search_move.eval() * 100.0).round().clamp(i16::MIN as f32, i16::MAX as f32) as i16
+            });
+        }
+
+        let next_board = board.make_move_new(mov);
+        history.push(&board, mov, &next_board);
+        board = next_board;
+    };
+
+    (records, result)
+}
+
+/// Self-play from random openings at a fixed depth. No book or online
+/// tablebase is consulted, so this runs offline.
+pub fn run_datagen(games: u32, depth: u8, out: &str, format: DataFormat)
+{
+    let file = match File::create(out)
+    {
+        Ok(file) => file,
+        Err(err) =>
+        {
+            eprintln!("can't create {}: {}", out, err);
+            return;
+        }
+    };
+
+    let mut writer = BufWriter::new(file);
+    let transposition_table = TranspostionTable::new(DATAGEN_HASH_SIZE);
+    let options = SearchOptions::default();
+    let limits = SearchLimits { depth: Some(depth), ..SearchLimits::default() };
+
+    let mut total = 0;
+
+    for game in 1..=games
+    {
+        let (records, result) = play_game(&transposition_table, &limits, &options);
+
+        let written = records.iter().try_for_each(|record| match format
+        {
+            DataFormat::Text => record.write_text(&mut writer, result),
+            DataFormat::Binary => record.write_binary(&mut writer, result)
+        });
+
+        if let Err(err) = written.and_then(|_| writer.flush())
+        {
+            eprintln!("can't write {}: {}", out, err);
+            return;
+        }
+
+        total += records.len();
+        println!("game {}/{}: result {:.1}, {} positions, {} in total", game, games, result, records.len(), total);
+    }
+}
@@ -26,11 +26,9 @@ impl EndGameTablebase
     pub fn get_move(&mut self, board: &Board) -> Option<ChessMove> 
     {
         let fen = board.to_string();
-        let cache = self.map.get(&fen);
-
-        if cache.is_some()
+        if let Some(cache) = self.map.get(&fen)
         {
-            return Some(ChessMove::from_str(cache.unwrap()).unwrap())
+            return Some(ChessMove::from_str(cache).unwrap())
         }
 
         let url = format!("https://tablebase.lichess.ovh/standard?fen={fen}");
@@ -43,9 +41,9 @@ impl EndGameTablebase
             .map_err(|_| {})
             .ok()?;
 
-        let cloned_board = board.clone();
+        let cloned_board = *board;
             
-        return if let Some(Value::Array(moves)) = response.get("moves") 
+        if let Some(Value::Array(moves)) = response.get("moves") 
         {
             let moves = moves
                 .iter()
@@ -62,7 +60,7 @@ impl EndGameTablebase
             for mov in &moves 
             {
                 self.map.insert(cloned_board.to_string(), mov.to_string());
-                cloned_board.make_move_new(mov.clone());
+                cloned_board.make_move_new(*mov);
             }
 
             Some(moves[0])
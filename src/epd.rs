@@ -0,0 +1,241 @@
+use std::fs;
+use std::str::FromStr;
+use std::sync::Arc;
+use chess::{Board, ChessMove};
+use shakmaty::fen::Fen;
+use shakmaty::san::SanPlus;
+use shakmaty::{CastlingMode, Chess};
+use crate::consts::EPD_HASH_SIZE;
+use crate::game_history::GameHistory;
+use crate::move_string_conversion::move_to_string;
+use crate::opening_book::NoBook;
+use crate::search::{engine, uci_score};
+use crate::search_limits::SearchLimits;
+use crate::search_options::SearchOptions;
+use crate::search_signals::SearchSignals;
+use crate::shakmaty_conversion::chess_move;
+use crate::transposition_table::TranspostionTable;
+use crate::uci::parse_fen;
+
+/// One EPD record: a position and the operations the runner understands.
+struct EpdPosition
+{
+    board: Board,
+    history: GameHistory,
+    id: Option<String>,
+    best_moves: Vec<(ChessMove, String)>,
+    avoid_moves: Vec<(ChessMove, String)>,
+    points: Vec<(ChessMove, u32)>
+}
+
+/// Reads a move written in SAN, as EPD requires, or in UCI notation, which
+/// some suites use instead.
+fn parse_move(board: &Board, text: &str) -> Option<ChessMove>
+{
+    let position = Fen::from_str(&board.to_string())
+        .ok()?
+        .into_position::<Chess>(CastlingMode::Standard)
+        .ok()?;
+
+    let mov = match SanPlus::from_str(text).ok().and_then(|san| san.san.to_move(&position).ok())
+    {
+        Some(mov) => chess_move(&mov)?,
+        None => ChessMove::from_str(text).ok()?
+    };
+
+    if board.legal(mov) { Some(mov) } else { None }
+}
+
+/// Splits the operations after the position on `;`, leaving quoted operands intact.
+fn split_operations(text: &str) -> Vec<String>
+{
+    let mut operations = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for character in text.chars()
+    {
+        match character
+        {
+            '"' => quoted = !quoted,
+            ';' if !quoted =>
+            {
+                operations.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        current.push(character);
+    }
+
+    operations.push(current.trim().to_string());
+    operations.into_iter().filter(|operation| !operation.is_empty()).collect()
+}
+
+fn parse_moves(board: &Board, operand: &str) -> Result<Vec<(ChessMove, String)>, String>
+{
+    operand
+        .split_whitespace()
+        .map(|text| parse_move(board, text).map(|mov| (mov, text.to_string())).ok_or_else(|| format!("illegal move {}", text)))
+        .collect()
+}
+
+/// STS puts the points of every rewarded move in `c0`, as in
+/// `c0 "Qxe4=10, Rb8=4"`. Other suites use `c0` for plain comments.
+fn parse_points(board: &Board, operand: &str) -> Vec<(ChessMove, u32)>
+{
+    operand
+        .split(',')
+        .map_while(|entry|
+        {
+            let (text, points) = entry.trim().split_once('=')?;
+            Some((parse_move(board, text.trim())?, points.trim().parse::<u32>().ok()?))
+        })
+        .collect()
+}
+
+fn parse_epd(line: &str) -> Result<EpdPosition, String>
+{
+    let fields = line.split_whitespace().collect::<Vec<&str>>();
+
+    if fields.len() < 4
+    {
+        return Err(format!("missing position in {}", line));
+    }
+
+    let (board, history) = parse_fen(&format!("{} 0 1", fields[..4].join(" ")))?;
+    let operations = line.splitn(5, char::is_whitespace).nth(4).unwrap_or("");
+
+    let mut position = EpdPosition
+    {
+        board,
+        history,
+        id: None,
+        best_moves: Vec::new(),
+        avoid_moves: Vec::new(),
+        points: Vec::new()
+    };
+
+    for operation in split_operations(operations)
+    {
+        let (opcode, operand) = operation.split_once(char::is_whitespace).unwrap_or((&operation, ""));
+        let operand = operand.trim().trim_matches('"');
+
+        match opcode
+        {
+            "id" => position.id = Some(operand.to_string()),
+            "bm" => position.best_moves = parse_moves(&position.board, operand)?,
+            "am" => position.avoid_moves = parse_moves(&position.board, operand)?,
+            "c0" => position.points = parse_points(&position.board, operand),
+            _ => {}
+        }
+    }
+
+    Ok(position)
+}
+
+fn move_list(moves: &[(ChessMove, String)]) -> String
+{
+    moves.iter().map(|(_, text)| text.as_str()).collect::<Vec<&str>>().join(" ")
+}
+
+/// Runs `engine` for `movetime` milliseconds on every position of the EPD
+/// file, reporting which `bm`/`am` operations it satisfies and, for STS
+/// suites, the points its moves earn.
+pub fn run_epd(path: &str, movetime: u64)
+{
+    let content = match fs::read_to_string(path)
+    {
+        Ok(content) => content,
+        Err(err) =>
+        {
+            eprintln!("can't read {}: {}", path, err);
+            return;
+        }
+    };
+
+    let lines = content.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<&str>>();
+
+    let transposition_table = TranspostionTable::new(EPD_HASH_SIZE);
+    let options = SearchOptions::default();
+    let limits = SearchLimits { movetime: Some(movetime), ..SearchLimits::default() };
+
+    let (mut solved, mut tested) = (0, 0);
+    let (mut points, mut max_points) = (0, 0);
+
+    for (index, line) in lines.iter().enumerate()
+    {
+        let position = match parse_epd(line)
+        {
+            Ok(position) => position,
+            Err(err) =>
+            {
+                println!("{}/{} skipped: {}", index + 1, lines.len(), err);
+                continue;
+            }
+        };
+
+        transposition_table.clear();
+
+        let search_move = engine(
+            &position.board,
+            &position.history,
+            &mut NoBook,
+            &transposition_table,
+            &limits,
+            &options,
+            Arc::new(SearchSignals::new())
+        );
+
+        let Some(mov) = search_move.mov()
+        else
+        {
+            println!("{}/{} skipped: no legal move", index + 1, lines.len());
+            continue;
+        };
+
+        let is_best = position.best_moves.is_empty() || position.best_moves.iter().any(|(best, _)| *best == mov);
+        let is_avoided = position.avoid_moves.iter().any(|(avoid, _)| *avoid == mov);
+        let is_solved = is_best && !is_avoided;
+
+        tested += 1;
+        solved += is_solved as u32;
+
+        if !position.points.is_empty()
+        {
+            points += position.points.iter().find(|(rewarded, _)| *rewarded == mov).map_or(0, |(_, points)| *points);
+            max_points += position.points.iter().map(|(_, points)| *points).max().unwrap_or(0);
+        }
+
+        let id = position.id.as_deref().unwrap_or("");
+        let score = uci_score(search_move.eval(), position.board.side_to_move());
+
+        if is_solved
+        {
+            println!("{}/{} {} solved {} ({})", index + 1, lines.len(), id, move_to_string(mov), score);
+        }
+        else
+        {
+            let expected = if position.best_moves.is_empty()
+            {
+                format!("am {}", move_list(&position.avoid_moves))
+            }
+            else
+            {
+                format!("bm {}", move_list(&position.best_moves))
+            };
+
+            println!("{}/{} {} failed {} ({}), expected {}", index + 1, lines.len(), id, move_to_string(mov), score, expected);
+        }
+    }
+
+    println!();
+    println!("Solved {}/{} ({:.1}%)", solved, tested, solved as f32 * 100.0 / tested.max(1) as f32);
+
+    if max_points > 0
+    {
+        println!("STS points {}/{} ({:.1}%)", points, max_points, points as f32 * 100.0 / max_points as f32);
+    }
+}
@@ -1,11 +1,14 @@
-use std::collections::HashMap;
-
-use chess::{Board, BoardStatus, ChessMove, Color, File, MoveGen, Piece, Rank, Square, ALL_SQUARES, EMPTY};
+use chess::{BitBoard, Board, BoardStatus, ChessMove, Color, File, MoveGen, Piece, Rank, Square, ALL_PIECES, ALL_SQUARES, EMPTY};
 use chess::BoardStatus::{Checkmate, Stalemate};
 use chess::Color::{White, Black};
-use crate::consts::{CONTROLLING_SQUARE, CONTROLLING_SQUARE_OPENING, DEFENDING_PIECE, DEFENDING_PIECE_OPENING, ENDGAME_KING_DISTANCE, ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE, GOOD_KNIGHT, KING_MOVED_NOT_ENDGAME, MAX_PIECE_FOR_ENDGAME, OPENING_FOR_DIFF_EVAL, OPENING_PIECES_FOR_KING_SAFETY, OPENING_PIECES_FOR_PIECE_SAFETY, OPENING_QUEEN_SAFETY, PAWN_CHAIN_BONUS, PAWN_ON_SAFE_FILE_DISADVANTAGE, PAWN_SHIELD_SCORE, ROOK_ON_7TH_RANK_BONUS};
-use crate::material::material;
-use crate::piece_table::{king_square_value, pawn_square_value};
+use crate::consts::{PIECE_PHASE, TOTAL_PHASE};
+use crate::eval_params::EvalParams;
+use crate::eval_trace::{EvalTerm, EvalTrace};
+use crate::pawn_hash_table::PawnHashTable;
+use crate::pawn_structure::{passed_pawn_bonus, pawn_key, PawnEntry};
+use crate::piece_table::piece_square_value;
+use crate::see::see_with_values;
+use crate::score::Score;
 
 pub fn is_terminal(status: BoardStatus) -> bool 
 {
@@ -32,6 +35,32 @@ pub fn count_all_pieces(board: &Board) -> u8
     res
 }
 
+/// Material based game phase, `TOTAL_PHASE` with every piece on the board
+/// down to 0 with only kings and pawns.
+pub fn game_phase(board: &Board) -> i32
+{
+    let phase = ALL_PIECES
+        .iter()
+        .map(|piece| board.pieces(*piece).popcnt() as i32 * PIECE_PHASE[piece.to_index()])
+        .sum::<i32>();
+
+    phase.min(TOTAL_PHASE)
+}
+
+/// Share of `color`'s knights and bishops still on their starting squares.
+fn undeveloped_minors(board: &Board, color: Color) -> f32
+{
+    let home = match color
+    {
+        White => [Square::B1, Square::C1, Square::F1, Square::G1],
+        Black => [Square::B8, Square::C8, Square::F8, Square::G8]
+    };
+
+    let minors = (board.pieces(Piece::Knight) | board.pieces(Piece::Bishop)) & board.color_combined(color);
+
+    home.iter().filter(|square| minors & BitBoard::from_square(**square) != EMPTY).count() as f32 / home.len() as f32
+}
+
 fn invert_color(color: Color) -> Color
 {
     match color 
@@ -46,15 +75,13 @@ fn is_valid_file_rank(file: i8, rank: i8) -> bool
     (0..=7).contains(&file) && (0..=7).contains(&rank)
 }
 
-fn is_piece_defended(board: &Board, sq: Square, color: Color, is_endgame: bool, curr_eval: &mut f32) -> bool
+fn is_piece_defended(board: &Board, sq: Square, color: Color) -> bool
 {
     let queen_directions: Vec<(i8, i8)> = vec![
         (-1, 1), (1, 1), (1, -1), (-1, -1), // Diagonals
         (1, 0), (0, 1), (0, -1), (-1, 0)   // Straights
     ];
 
-    let sq_piece_type = board.piece_on(sq).unwrap();
-
     let knight_directions: Vec<(i8, i8)> = vec![
         (-2, 1), (-2, -1), (1, -2), (-1, -2),
         (2, 1), (2, -1), (1, 2), (-1, 2)
@@ -90,16 +117,6 @@ fn is_piece_defended(board: &Board, sq: Square, color: Color, is_endgame: bool,
                     let rook_defend = piece == Piece::Rook && (direction.0 == 0 || direction.1 == 0);
                     let queen_defend = piece == Piece::Queen;
 
-                    if pawn_defend && sq_piece_type == Piece::Pawn
-                    {
-                        *curr_eval += white_score(PAWN_CHAIN_BONUS, color)
-                    }
-
-                    if rook_defend && sq_piece_type == Piece::Pawn && is_endgame
-                    {
-                        *curr_eval += white_score(ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE, color)
-                    }
-
                     return pawn_defend || king_defend || bishop_defend || rook_defend || queen_defend
                 }
                 break;
@@ -130,14 +147,15 @@ fn is_piece_defended(board: &Board, sq: Square, color: Color, is_endgame: bool,
 fn distance(sq: Square, sq2: Square) -> u8 
 {
     let sqrf = square_index(sq).0.abs_diff(square_index(sq2).0);
-    let sqfd = square_index(sq).0.abs_diff(square_index(sq2).0);
+    let sqfd = square_index(sq).1.abs_diff(square_index(sq2).1);
 
     sqrf.max(sqfd) - 1
 }
 
-pub fn is_bad_king_move(board: &Board, mov: &ChessMove, pieces: u8) -> bool
+/// A king move other than castling, a capture or a way out of check, which
+/// only counts as mobility as the endgame approaches.
+fn is_quiet_king_move(board: &Board, mov: &ChessMove) -> bool
 {
-    let is_opening_for_king_safety = pieces <= OPENING_PIECES_FOR_KING_SAFETY;
     let source = mov.get_source();
     let dest = mov.get_dest();
 
@@ -145,22 +163,61 @@ pub fn is_bad_king_move(board: &Board, mov: &ChessMove, pieces: u8) -> bool
     let is_capturing = board.piece_on(dest).is_some();
     let is_check = board.checkers() != &EMPTY;
 
-    is_opening_for_king_safety && !is_castling && !is_capturing && !is_check &&
-    (
-        mov.get_source() == board.king_square(White) ||
-        mov.get_source() == board.king_square(Black)
-    )
+    !is_castling && !is_capturing && !is_check && board.piece_on(source) == Some(Piece::King)
+}
+
+/// The squares in front of `color`'s king and on either side of it that are
+/// on the board, `None` with the king on the far rank.
+fn pawn_shield_squares(board: &Board, color: Color) -> Option<Vec<Square>>
+{
+    let (rank, file) = square_index(board.king_square(color));
+    let rank = rank as i8 + if color == White { 1 } else { -1 };
+
+    if !is_valid_file_rank(file as i8, rank)
+    {
+        return None;
+    }
+
+    let squares = (-1..=1)
+        .map(|file_change| file as i8 + file_change)
+        .filter(|file| is_valid_file_rank(*file, rank))
+        .map(|file| Square::make_square(Rank::from_index(rank as usize), File::from_index(file as usize)))
+        .collect();
+
+    Some(squares)
+}
+
+pub fn eval(board: &Board, legal_moves: Vec<ChessMove>, plies: i32, params: &EvalParams) -> f32
+{
+    evaluate(board, legal_moves, plies, params, None, &mut EvalTrace::new())
+}
+
+/// `eval` looking the pawn structure up in `pawn_table`.
+pub fn eval_with_pawn_table(board: &Board, legal_moves: Vec<ChessMove>, plies: i32, params: &EvalParams, pawn_table: &mut PawnHashTable) -> f32
+{
+    evaluate(board, legal_moves, plies, params, Some(pawn_table), &mut EvalTrace::new())
+}
+
+/// The evaluation of `board` broken down by term, for the `eval` command.
+pub fn trace_eval(board: &Board, plies: i32, params: &EvalParams) -> EvalTrace
+{
+    let mut trace = EvalTrace::new();
+    let score = evaluate(board, MoveGen::new_legal(board).collect(), plies, params, None, &mut trace);
+
+    trace.set_score(score);
+    trace
 }
 
-pub fn eval(
+fn evaluate(
     board: &Board, 
     legal_moves: Vec<ChessMove>, 
     plies: i32,
-    _log: bool
+    params: &EvalParams,
+    pawn_table: Option<&mut PawnHashTable>,
+    trace: &mut EvalTrace
 ) -> f32 
 {
     let pieces = count_all_pieces(board);
-    let mut pawn_on_files: u8 = 0;
 
     if board.status() == Checkmate 
     {
@@ -175,190 +232,143 @@ pub fn eval(
         return 0.0;
     }
 
-    let mut score_for_white = 0.0;
-
-    let is_endgame = pieces <= MAX_PIECE_FOR_ENDGAME;
-    let is_opening_for_piece_safety = plies <= OPENING_PIECES_FOR_PIECE_SAFETY;
-    let is_opening_for_king_safety = pieces <= OPENING_PIECES_FOR_KING_SAFETY;
+    let phase = game_phase(board);
+    trace.set_phase(phase);
 
-    let mut captured = HashMap::new();
-    let mut max_captured = 0.0;
+    let mut score_for_white = Score::ZERO;
 
     let mut pawn_shield = None;
+    let mut material = [Score::ZERO; 2];
 
-    if is_opening_for_king_safety 
+    let pawns = match pawn_table
     {
-        let colors = [White, Black];
-        for _color in colors 
+        Some(pawn_table) => pawn_table.probe(board, params),
+        None => PawnEntry::new(board, pawn_key(board), params)
+    };
+
+    for color in [White, Black]
+    {
+        score_for_white += trace.add(EvalTerm::PawnStructure, color, pawns.structure[color.to_index()]);
+
+        for square in pawns.passed[color.to_index()]
         {
-            let white_king = square_index(board.king_square(White));
-            let rank = white_king.0 as i8;
-            let file = white_king.1 as i8;
-
-            let rank_change: i8 = if _color == White {1} else {-1};
-                
-            let sq1 = Square::make_square(Rank::from_index((rank+rank_change) as usize), File::from_index((file-1) as usize));
-            let sq2 = Square::make_square(Rank::from_index((rank+rank_change) as usize), File::from_index(file as usize));
-            let sq3 = Square::make_square(Rank::from_index((rank+rank_change) as usize), File::from_index((file+1) as usize));
-
-            if board.piece_on(sq1).is_some()
-                && board.piece_on(sq2).is_some()
-                && board.piece_on(sq3).is_some()
-            {
-                score_for_white += white_score(PAWN_SHIELD_SCORE, _color);
-                pawn_shield = Some(_color)
-            }
+            score_for_white += trace.add(EvalTerm::PassedPawns, color, passed_pawn_bonus(board, square, color, params));
         }
     }
 
-    if _log{println!("Eval step 1: {}", score_for_white)};
+    for color in [White, Black]
+    {
+        let shield = pawn_shield_squares(board, color);
+
+        if shield.is_some_and(|squares| squares.iter().all(|square| board.piece_on(*square).is_some()))
+        {
+            score_for_white += trace.add(EvalTerm::PawnShield, color, params.pawn_shield_score);
+            pawn_shield = Some(color)
+        }
+    }
 
     for square in ALL_SQUARES 
     {
         if let Some(piece) = board.piece_on(square) 
         {
             let color = board.color_on(square).unwrap();
-            let (rank, file) = square_index(square);
+            let (rank, _) = square_index(square);
 
             if piece == Piece::Rook && color == White && rank == 6 
             {
-                score_for_white += ROOK_ON_7TH_RANK_BONUS
+                score_for_white += trace.add(EvalTerm::RookOn7th, White, params.rook_on_7th_rank_bonus)
             }
 
-            if is_endgame && piece == Piece::Pawn 
+            if piece == Piece::Pawn 
             {
-                let file_index= square.get_file().to_index();
-                if ((pawn_on_files << file_index) & 1_u8) != 0
-                {
-                    score_for_white -= white_score(PAWN_ON_SAFE_FILE_DISADVANTAGE, color)
-                }
-
-                pawn_on_files |= 1 << square.get_file().to_index();
-
                 let king = board.king_square(color);
                 let enemy_king = board.king_square(invert_color(color));
 
-                score_for_white += distance(king, square) as f32 / 1.6;
-                score_for_white -= distance(enemy_king, square) as f32 / 1.6;
-            }
+                // Kings belong next to the pawns, their own and the enemy's alike
+                let closer = distance(enemy_king, square) as f32 - distance(king, square) as f32;
 
-            if piece == Piece::Rook && color == Black && rank == 1 
-            {
-                score_for_white -= ROOK_ON_7TH_RANK_BONUS
+                score_for_white += trace.add(EvalTerm::KingPawnDistance, color, params.king_pawn_distance * closer);
             }
 
-            if piece == Piece::Knight && is_opening_for_piece_safety
+            if piece == Piece::Rook && color == Black && rank == 1 
             {
-                if color == White && (square == Square::F3 || square == Square::C3) 
-                {
-                    score_for_white += GOOD_KNIGHT
-                }
-
-                if color == Black && (square == Square::C6 || square == Square::F6) 
-                {
-                    score_for_white -= GOOD_KNIGHT
-                }
+                score_for_white += trace.add(EvalTerm::RookOn7th, Black, params.rook_on_7th_rank_bonus)
             }
 
-            if piece == Piece::King && is_opening_for_king_safety
+            if piece == Piece::King
             {
                 let plies = if plies == 0 {8} else {plies};
 
                 if color == Black && rank != 7
                 {
-                    score_for_white += KING_MOVED_NOT_ENDGAME/plies as f32;
+                    score_for_white += trace.add(EvalTerm::KingSafety, Black, -(params.king_moved_not_endgame / plies as f32));
                 }
 
                 if color == White && rank != 0
                 {
-                    score_for_white -= KING_MOVED_NOT_ENDGAME/plies as f32
+                    score_for_white += trace.add(EvalTerm::KingSafety, White, -(params.king_moved_not_endgame / plies as f32))
                 }
             }
 
-            if piece == Piece::Queen && is_opening_for_piece_safety
+            // An early queen sortie costs less with every minor piece developed
+            if piece == Piece::Queen
             {
                 let white_range = 2..=7;
                 let black_range = 0..=3;
 
-                if _log{println!("{} {} {} {:?} {}", piece == Piece::Queen, color == White, white_range.contains(&rank), white_range, rank)};
+                let is_exposed = match color 
+                {
+                    White => white_range.contains(&rank),
+                    Black => black_range.contains(&rank)
+                };
 
-                score_for_white += match (piece, color) 
+                if is_exposed
                 {
-                    (Piece::Queen, White) if white_range.contains(&rank) => -OPENING_QUEEN_SAFETY,
-                    (Piece::Queen, Black) if black_range.contains(&rank) => OPENING_QUEEN_SAFETY,
-                    _ => 0.0
+                    score_for_white += trace.add(EvalTerm::QueenSafety, color, -(params.opening_queen_safety * undeveloped_minors(board, color)))
                 }
             }
             
             let opposite_king = board.king_square(invert_color(color));
 
-            if piece == Piece::Queen && !is_opening_for_king_safety && is_piece_defended(board, square, color, is_endgame, &mut score_for_white) && pawn_shield != Some(invert_color(color))
+            if piece == Piece::Queen && is_piece_defended(board, square, color) && pawn_shield != Some(invert_color(color))
             {
                 let distance = distance(opposite_king, square);
 
-                score_for_white += white_score(match distance 
+                score_for_white += trace.add(EvalTerm::QueenAttack, color, match distance 
                 {
-                    1 => 2.6,
-                    2 => 0.8,
-                    _ => 0.0
-                }, color);
+                    1 => params.queen_near_king_bonus,
+                    2 => params.queen_close_to_king_bonus,
+                    _ => Score::ZERO
+                });
             }
 
-            score_for_white += match piece 
-            {
-                Piece::Pawn => white_score(pawn_square_value(rank, file, color, is_endgame, board), color),
-                Piece::King => white_score(king_square_value(rank, file, color, is_endgame, board), color),
-                _ => white_score(material(Some(piece)), color),
-            };
+            material[color.to_index()] += params.material(Some(piece));
+            score_for_white += trace.add(EvalTerm::Material, color, params.material(Some(piece)));
+            score_for_white += trace.add(EvalTerm::PieceSquares, color, piece_square_value(piece, square, color, params));
         }
     }
 
-    if _log{println!("Eval step 2: {}", score_for_white)};
+    // The side to move can cash in the best exchange on offer
+    let piece_values = params.piece_values.map(|value| value.taper(phase));
+    let mut best_capture = 0.0_f32;
 
     for mov in &legal_moves
     {
-        if is_bad_king_move(board, mov, pieces) 
+        if board.piece_on(mov.get_dest()).is_some() 
         {
-            continue;
+            best_capture = best_capture.max(see_with_values(board, *mov, &piece_values));
         }
-
-        let source_piece = board.piece_on(mov.get_source());
-        let source_color = board.color_on(mov.get_source());
-
-        if let Some(dest_piece) = board.piece_on(mov.get_dest()) 
+        else if is_quiet_king_move(board, mov)
         {
-            let dest_color = board.color_on(mov.get_dest()).unwrap();
-            let dest_sq = mov.get_dest();
-                    
-            if let Some(val) = captured.get(&mov.get_source())
-            {
-                score_for_white += white_score(*val, source_color.unwrap());
-            }
-            else if !is_piece_defended(board, dest_sq, dest_color, is_endgame, &mut score_for_white)
-            {
-                let source_material = material(Some(dest_piece));
-
-                captured.insert(mov.get_source(), DEFENDING_PIECE);
-                score_for_white += white_score(0.0_f32.max(source_material-max_captured), source_color.unwrap());
-
-                max_captured = max_captured.max(source_material);
-            }
-            else 
-            {  
-                let source_material = material(Some(dest_piece))-material(source_piece);
-
-                captured.insert(mov.get_source(), DEFENDING_PIECE);
-                score_for_white += white_score(0.0_f32.max(source_material-max_captured), source_color.unwrap());
-
-                max_captured = max_captured.max(source_material);
-            }
+            score_for_white += trace.add(EvalTerm::Mobility, board.side_to_move(), Score::new(0.0, params.controlling_square.eg))
         }
         else 
         {
-            score_for_white += white_score(CONTROLLING_SQUARE, board.side_to_move())
+            score_for_white += trace.add(EvalTerm::Mobility, board.side_to_move(), params.controlling_square)
         }   
     }
-    if _log{println!("Eval step 3: {}", score_for_white)};
+
+    score_for_white += trace.add(EvalTerm::Captures, board.side_to_move(), Score::splat(best_capture));
 
     let flipped_board = board.null_move();
 
@@ -373,55 +383,33 @@ pub fn eval(
 
             if dest_piece.is_some()
             {
-                if is_endgame && dest_piece == Some(Piece::Pawn) && source_piece == Some(Piece::Pawn)
+                if dest_piece == Some(Piece::Pawn) && source_piece == Some(Piece::Pawn)
                 {
-                    score_for_white += white_score(ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE, flipped_board.side_to_move())
+                    score_for_white += trace.add(EvalTerm::DefendedPieces, flipped_board.side_to_move(), params.endgame_pawn_rook_defense_advantage)
                 }
 
-                score_for_white += white_score(
-                    if plies <= OPENING_FOR_DIFF_EVAL 
-                    {
-                        DEFENDING_PIECE_OPENING
-                    }
-                    else 
-                    {
-                        DEFENDING_PIECE
-                    }, 
-                    flipped_board.side_to_move()
-                )
+                score_for_white += trace.add(EvalTerm::DefendedPieces, flipped_board.side_to_move(), params.defending_piece)
             }
             else 
             {
-                score_for_white += white_score(
-                    if plies <= OPENING_FOR_DIFF_EVAL 
-                    {
-                        CONTROLLING_SQUARE_OPENING
-                    }
-                    else 
-                    {
-                        CONTROLLING_SQUARE
-                    }, 
-                    flipped_board.side_to_move()
-                )
+                score_for_white += trace.add(EvalTerm::Mobility, flipped_board.side_to_move(), params.controlling_square)
             }
         }
     }
 
-    if _log{println!("Eval step 4: {}", score_for_white)};
+    // The side ahead in material wants the kings close, to drive the other
+    // one to the edge
+    let material_diff = (material[White.to_index()] - material[Black.to_index()]).taper(phase);
 
-    if is_endgame 
+    if material_diff != 0.0
     {
-        let wk = board.king_square(White);
-        let bk = board.king_square(Black);
-
-        let diff = distance(wk, bk) as f32;
+        let ahead = if material_diff > 0.0 { White } else { Black };
+        let closeness = (6 - distance(board.king_square(White), board.king_square(Black))) as f32;
 
-        score_for_white += diff*score_for_white*ENDGAME_KING_DISTANCE
+        score_for_white += trace.add(EvalTerm::EndgameKingDistance, ahead, params.endgame_king_distance * closeness);
     }
 
-    if _log{println!("Eval step 5: {}", score_for_white)};
-
-    score_for_white
+    score_for_white.taper(phase)
 }
 
 fn square_index(square: Square) -> (u8, u8) 
@@ -429,3 +417,64 @@ fn square_index(square: Square) -> (u8, u8)
     let idx = square.to_index();
     ((idx / 8) as u8, (idx % 8) as u8)
 }
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    /// `fen` with the board turned upside down and the colours swapped.
+    fn mirror(fen: &str) -> String
+    {
+        let swap_case = |text: &str| text.chars().map(|c| if c.is_uppercase() { c.to_ascii_lowercase() } else { c.to_ascii_uppercase() }).collect::<String>();
+        let fields = fen.split_whitespace().collect::<Vec<&str>>();
+
+        let placement = fields[0].split('/').rev().map(swap_case).collect::<Vec<String>>().join("/");
+        let side = if fields[1] == "w" { "b" } else { "w" };
+        let castling = if fields[2] == "-" { "-".to_string() } else { swap_case(fields[2]) };
+        let en_passant = match fields[3]
+        {
+            "-" => "-".to_string(),
+            square => format!("{}{}", &square[..1], if &square[1..] == "3" { 6 } else { 3 })
+        };
+
+        format!("{} {} {} {} {}", placement, side, castling, en_passant, fields[4..].join(" "))
+    }
+
+    fn evaluate_fen(fen: &str) -> f32
+    {
+        let board = Board::from_str(fen).unwrap();
+
+        eval(&board, MoveGen::new_legal(&board).collect(), 20, &EvalParams::default())
+    }
+
+    #[test]
+    fn mirrored_position_negates_score()
+    {
+        let fens =
+        [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1",
+            "8/8/1p1r1k2/p1pPN1p1/P3KnP1/1P6/8/3R4 b - - 0 1",
+            "7k/P7/8/8/8/8/6p1/K7 w - - 0 1"
+        ];
+
+        for fen in fens
+        {
+            let (score, mirrored) = (evaluate_fen(fen), evaluate_fen(&mirror(fen)));
+
+            assert!((score + mirrored).abs() < 1e-3, "{} gives {}, mirrored {}", fen, score, mirrored);
+        }
+    }
+
+    #[test]
+    fn side_up_a_rook_is_ahead()
+    {
+        assert!(evaluate_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - - 0 1") > 3.0);
+        assert!(evaluate_fen("r5k1/5ppp/8/8/8/8/5PPP/6K1 w - - 0 1") < -3.0);
+    }
+}
@@ -0,0 +1,375 @@
+use std::fs;
+use std::path::Path;
+use chess::{Piece, ALL_PIECES};
+use serde_json::{Map, Value};
+use crate::consts::{BACKWARD_PAWN, CONNECTED_PAWN, CONTROLLING_SQUARE, DEFENDING_PIECE, DOUBLED_PAWN, ENDGAME_KING_DISTANCE, ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE, ISOLATED_PAWN, KING_MOVED_NOT_ENDGAME, KING_PAWN_DISTANCE, OPENING_QUEEN_SAFETY, PASSED_PAWN, PASSED_PAWN_FREE_PATH, PAWN_SHIELD_SCORE, QUEEN_CLOSE_TO_KING_BONUS, QUEEN_NEAR_KING_BONUS, ROOK_ON_7TH_RANK_BONUS};
+use crate::material::material;
+use crate::piece_table::{EG_TABLES, MG_TABLES};
+use crate::score::Score;
+
+type ScalarWeight = fn(&mut EvalParams) -> &mut Score;
+
+/// The weights that aren't tables, by name.
+const SCALARS: [(&str, ScalarWeight); 23] =
+[
+    ("pawn_value", |params| &mut params.piece_values[Piece::Pawn.to_index()]),
+    ("knight_value", |params| &mut params.piece_values[Piece::Knight.to_index()]),
+    ("bishop_value", |params| &mut params.piece_values[Piece::Bishop.to_index()]),
+    ("rook_value", |params| &mut params.piece_values[Piece::Rook.to_index()]),
+    ("queen_value", |params| &mut params.piece_values[Piece::Queen.to_index()]),
+    ("king_value", |params| &mut params.piece_values[Piece::King.to_index()]),
+    ("endgame_king_distance", |params| &mut params.endgame_king_distance),
+    ("opening_queen_safety", |params| &mut params.opening_queen_safety),
+    ("rook_on_7th_rank_bonus", |params| &mut params.rook_on_7th_rank_bonus),
+    ("king_moved_not_endgame", |params| &mut params.king_moved_not_endgame),
+    ("pawn_shield_score", |params| &mut params.pawn_shield_score),
+    ("controlling_square", |params| &mut params.controlling_square),
+    ("defending_piece", |params| &mut params.defending_piece),
+    ("doubled_pawn", |params| &mut params.doubled_pawn),
+    ("isolated_pawn", |params| &mut params.isolated_pawn),
+    ("backward_pawn", |params| &mut params.backward_pawn),
+    ("connected_pawn", |params| &mut params.connected_pawn),
+    ("passed_pawn", |params| &mut params.passed_pawn),
+    ("passed_pawn_free_path", |params| &mut params.passed_pawn_free_path),
+    ("endgame_pawn_rook_defense_advantage", |params| &mut params.endgame_pawn_rook_defense_advantage),
+    ("queen_near_king_bonus", |params| &mut params.queen_near_king_bonus),
+    ("queen_close_to_king_bonus", |params| &mut params.queen_close_to_king_bonus),
+    ("king_pawn_distance", |params| &mut params.king_pawn_distance)
+];
+
+const TABLE_NAMES: [&str; 6] = ["pawn_table", "knight_table", "bishop_table", "rook_table", "queen_table", "king_table"];
+
+const PHASES: [&str; 2] = ["mg", "eg"];
+
+/// Marks a `setoption` name as an evaluation weight, as in
+/// `Eval_isolated_pawn_mg` or `Eval_pawn_table_eg[12]`.
+pub const EVAL_OPTION_PREFIX: &str = "Eval_";
+
+/// Every weight of the evaluation, each with a middlegame and an endgame
+/// value. The defaults are the constants of `consts.rs`, `material.rs` and
+/// `piece_table.rs`, a parameter file can override any of them by its snake
+/// case name followed by `_mg` or `_eg`, `setoption` by that name behind
+/// `EVAL_OPTION_PREFIX`.
+#[derive(Clone)]
+pub struct EvalParams
+{
+    pub piece_values: [Score; 6],
+    pub piece_tables: [[Score; 64]; 6],
+    pub endgame_king_distance: Score,
+    pub opening_queen_safety: Score,
+    pub rook_on_7th_rank_bonus: Score,
+    pub king_moved_not_endgame: Score,
+    pub pawn_shield_score: Score,
+    pub controlling_square: Score,
+    pub defending_piece: Score,
+    pub doubled_pawn: Score,
+    pub isolated_pawn: Score,
+    pub backward_pawn: Score,
+    pub connected_pawn: Score,
+    pub passed_pawn: Score,
+    pub passed_pawn_free_path: Score,
+    pub endgame_pawn_rook_defense_advantage: Score,
+    pub queen_near_king_bonus: Score,
+    pub queen_close_to_king_bonus: Score,
+    pub king_pawn_distance: Score
+}
+
+impl Default for EvalParams
+{
+    fn default() -> Self
+    {
+        EvalParams
+        {
+            piece_values: ALL_PIECES.map(|piece| Score::splat(material(Some(piece)))),
+            piece_tables: ALL_PIECES.map(|piece|
+            {
+                let (mg, eg) = (&MG_TABLES[piece.to_index()], &EG_TABLES[piece.to_index()]);
+                std::array::from_fn(|square| Score::new(mg[square], eg[square]))
+            }),
+            endgame_king_distance: ENDGAME_KING_DISTANCE,
+            opening_queen_safety: OPENING_QUEEN_SAFETY,
+            rook_on_7th_rank_bonus: ROOK_ON_7TH_RANK_BONUS,
+            king_moved_not_endgame: KING_MOVED_NOT_ENDGAME,
+            pawn_shield_score: PAWN_SHIELD_SCORE,
+            controlling_square: CONTROLLING_SQUARE,
+            defending_piece: DEFENDING_PIECE,
+            doubled_pawn: DOUBLED_PAWN,
+            isolated_pawn: ISOLATED_PAWN,
+            backward_pawn: BACKWARD_PAWN,
+            connected_pawn: CONNECTED_PAWN,
+            passed_pawn: PASSED_PAWN,
+            passed_pawn_free_path: PASSED_PAWN_FREE_PATH,
+            endgame_pawn_rook_defense_advantage: ENDGAME_PAWN_ROOK_DEFENSE_ADVANTAGE,
+            queen_near_king_bonus: QUEEN_NEAR_KING_BONUS,
+            queen_close_to_king_bonus: QUEEN_CLOSE_TO_KING_BONUS,
+            king_pawn_distance: KING_PAWN_DISTANCE
+        }
+    }
+}
+
+/// One phase of `score`, by the `mg` or `eg` suffix of a weight name.
+fn phase_mut<'a>(score: &'a mut Score, phase: &str) -> Option<&'a mut f32>
+{
+    match phase
+    {
+        "mg" => Some(&mut score.mg),
+        "eg" => Some(&mut score.eg),
+        _ => None
+    }
+}
+
+impl EvalParams
+{
+    pub fn material(&self, piece: Option<Piece>) -> Score
+    {
+        piece.map_or(Score::ZERO, |piece| self.piece_values[piece.to_index()])
+    }
+
+    fn scalar_mut(&mut self, name: &str) -> Option<&mut Score>
+    {
+        SCALARS
+            .iter()
+            .find(|(scalar, _)| *scalar == name)
+            .map(|(_, weight)| weight(self))
+    }
+
+    fn table_mut(&mut self, name: &str) -> Option<&mut [Score; 64]>
+    {
+        TABLE_NAMES
+            .iter()
+            .position(|table| *table == name)
+            .map(|piece| &mut self.piece_tables[piece])
+    }
+
+    /// Names of every single weight, table entries written like
+    /// `pawn_table_mg[12]`.
+    pub fn weight_names() -> Vec<String>
+    {
+        let scalars = SCALARS
+            .iter()
+            .flat_map(|(name, _)| PHASES.map(|phase| format!("{}_{}", name, phase)));
+
+        let tables = TABLE_NAMES
+            .iter()
+            .flat_map(|table| PHASES.map(|phase| format!("{}_{}", table, phase)))
+            .flat_map(|table| (0..64).map(move |index| format!("{}[{}]", table, index)));
+
+        scalars.chain(tables).collect()
+    }
+
+    /// One weight, either a scalar like `isolated_pawn_mg` or a table entry
+    /// like `pawn_table_eg[12]`.
+    pub fn weight_mut(&mut self, name: &str) -> Option<&mut f32>
+    {
+        let (name, index) = match name.strip_suffix(']').and_then(|name| name.split_once('['))
+        {
+            Some((table, index)) => (table, Some(index.parse::<usize>().ok()?)),
+            None => (name, None)
+        };
+
+        let (name, phase) = name.rsplit_once('_')?;
+
+        let score = match index
+        {
+            Some(index) => self.table_mut(name)?.get_mut(index)?,
+            None => self.scalar_mut(name)?
+        };
+
+        phase_mut(score, phase)
+    }
+
+    pub fn set(&mut self, name: &str, value: &str) -> Result<(), String>
+    {
+        let value = value.parse::<f32>().map_err(|_| format!("invalid value {} for {}", value, name))?;
+
+        *self.weight_mut(name).ok_or_else(|| format!("unknown evaluation parameter {}", name))? = value;
+        Ok(())
+    }
+
+    /// Reads a `.toml` or `.json` file mapping parameter names to numbers,
+    /// or to arrays of 64 numbers for the tables. Weights the file leaves
+    /// out keep their defaults.
+    pub fn load(path: &str) -> Result<EvalParams, String>
+    {
+        let text = fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+
+        let document = if Path::new(path).extension().is_some_and(|extension| extension == "toml")
+        {
+            toml::from_str::<Value>(&text).map_err(|err| err.to_string())?
+        }
+        else
+        {
+            serde_json::from_str::<Value>(&text).map_err(|err| err.to_string())?
+        };
+
+        let entries = document.as_object().ok_or_else(|| format!("{} is not a table of parameters", path))?;
+        let mut params = EvalParams::default();
+
+        for (name, value) in entries
+        {
+            match value
+            {
+                Value::Number(number) =>
+                {
+                    let weight = params.weight_mut(name).ok_or_else(|| format!("unknown evaluation parameter {}", name))?;
+                    *weight = number.as_f64().unwrap_or_default() as f32;
+                }
+                Value::Array(values) =>
+                {
+                    let (table, phase) = name
+                        .rsplit_once('_')
+                        .filter(|(_, phase)| PHASES.contains(phase))
+                        .and_then(|(table, phase)| Some((params.table_mut(table)?, phase)))
+                        .ok_or_else(|| format!("unknown evaluation table {}", name))?;
+
+                    if values.len() != table.len() || values.iter().any(|value| !value.is_number())
+                    {
+                        return Err(format!("{} needs {} numbers", name, table.len()));
+                    }
+
+                    for (score, value) in table.iter_mut().zip(values)
+                    {
+                        *phase_mut(score, phase).unwrap() = value.as_f64().unwrap_or_default() as f32;
+                    }
+                }
+                _ => return Err(format!("invalid value for {}", name))
+            }
+        }
+
+        Ok(params)
+    }
+
+    /// Writes every weight in the format `load` reads, TOML or JSON by the
+    /// extension of `path`.
+    pub fn save(&self, path: &str) -> Result<(), String>
+    {
+        // Going through the shortest f32 representation keeps 0.87 from
+        // turning into 0.8700000047683716
+        let number = |weight: f32| Value::from(weight.to_string().parse::<f64>().unwrap_or_default());
+
+        let mut params = self.clone();
+        let mut document = Map::new();
+
+        for (name, weight) in SCALARS
+        {
+            for phase in PHASES
+            {
+                let weight = *phase_mut(weight(&mut params), phase).unwrap();
+                document.insert(format!("{}_{}", name, phase), number(weight));
+            }
+        }
+
+        for name in TABLE_NAMES
+        {
+            for phase in PHASES
+            {
+                let table = params.table_mut(name).unwrap();
+                let weights = table.iter_mut().map(|score| number(*phase_mut(score, phase).unwrap())).collect();
+
+                document.insert(format!("{}_{}", name, phase), Value::Array(weights));
+            }
+        }
+
+        let text = if Path::new(path).extension().is_some_and(|extension| extension == "toml")
+        {
+            toml::to_string(&document).map_err(|err| err.to_string())?
+        }
+        else
+        {
+            serde_json::to_string_pretty(&document).map_err(|err| err.to_string())?
+        };
+
+        fs::write(path, text).map_err(|err| format!("can't write {}: {}", path, err))
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn weights(params: &EvalParams) -> Vec<f32>
+    {
+        let mut params = params.clone();
+        EvalParams::weight_names().iter().map(|name| *params.weight_mut(name).unwrap()).collect()
+    }
+
+    /// Every weight different from its default and from all others.
+    fn tuned() -> EvalParams
+    {
+        let mut params = EvalParams::default();
+
+        for (index, name) in EvalParams::weight_names().iter().enumerate()
+        {
+            *params.weight_mut(name).unwrap() += 0.01 * (index + 1) as f32;
+        }
+
+        params
+    }
+
+    #[test]
+    fn every_scalar_can_be_set_by_phase()
+    {
+        for (name, weight) in SCALARS
+        {
+            let mut params = EvalParams::default();
+
+            params.set(&format!("{}_mg", name), "1.25").unwrap();
+            params.set(&format!("{}_eg", name), "-0.5").unwrap();
+
+            let score = weight(&mut params);
+            assert_eq!((score.mg, score.eg), (1.25, -0.5), "{}", name);
+        }
+    }
+
+    #[test]
+    fn bad_names_and_values_are_rejected()
+    {
+        let mut params = EvalParams::default();
+
+        assert!(params.set("isolated_pawn", "1").is_err());
+        assert!(params.set("isolated_pawn_xg", "1").is_err());
+        assert!(params.set("isolated_pawn_mg", "many").is_err());
+        assert!(params.set("pawn_table_mg[64]", "1").is_err());
+        assert!(params.set("pawn_table_mg[12]", "0.75").is_ok());
+        assert_eq!(params.piece_tables[Piece::Pawn.to_index()][12].mg, 0.75);
+    }
+
+    #[test]
+    fn saved_files_load_back()
+    {
+        let params = tuned();
+
+        for extension in ["toml", "json"]
+        {
+            let path = std::env::temp_dir().join(format!("sigma0-params-{}.{}", std::process::id(), extension));
+            let path = path.to_str().unwrap();
+
+            params.save(path).unwrap();
+            let loaded = EvalParams::load(path);
+            fs::remove_file(path).unwrap();
+
+            assert_eq!(weights(&loaded.unwrap()), weights(&params), "{}", extension);
+        }
+    }
+
+    #[test]
+    fn partial_files_keep_the_defaults()
+    {
+        let path = std::env::temp_dir().join(format!("sigma0-partial-{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        fs::write(path, r#"{ "doubled_pawn_eg": -0.5 }"#).unwrap();
+        let loaded = EvalParams::load(path).unwrap();
+
+        fs::write(path, r#"{ "pawn_table_mg": [1, 2] }"#).unwrap();
+        let short_table = EvalParams::load(path);
+        fs::remove_file(path).unwrap();
+
+        let mut expected = EvalParams::default();
+        expected.doubled_pawn.eg = -0.5;
+
+        assert_eq!(weights(&loaded), weights(&expected));
+        assert!(short_table.is_err());
+    }
+}
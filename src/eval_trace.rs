@@ -0,0 +1,138 @@
+use std::fmt;
+use chess::Color;
+use crate::consts::TOTAL_PHASE;
+use crate::score::Score;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EvalTerm
+{
+    Material,
+    PieceSquares,
+    PawnShield,
+    PawnStructure,
+    PassedPawns,
+    RookOn7th,
+    KingSafety,
+    QueenSafety,
+    QueenAttack,
+    Mobility,
+    Captures,
+    DefendedPieces,
+    KingPawnDistance,
+    EndgameKingDistance
+}
+
+const ALL_TERMS: [EvalTerm; 14] =
+[
+    EvalTerm::Material,
+    EvalTerm::PieceSquares,
+    EvalTerm::PawnShield,
+    EvalTerm::PawnStructure,
+    EvalTerm::PassedPawns,
+    EvalTerm::RookOn7th,
+    EvalTerm::KingSafety,
+    EvalTerm::QueenSafety,
+    EvalTerm::QueenAttack,
+    EvalTerm::Mobility,
+    EvalTerm::Captures,
+    EvalTerm::DefendedPieces,
+    EvalTerm::KingPawnDistance,
+    EvalTerm::EndgameKingDistance
+];
+
+impl EvalTerm
+{
+    fn name(self) -> &'static str
+    {
+        match self
+        {
+            EvalTerm::Material => "Material",
+            EvalTerm::PieceSquares => "Piece squares",
+            EvalTerm::PawnShield => "Pawn shield",
+            EvalTerm::PawnStructure => "Pawn structure",
+            EvalTerm::PassedPawns => "Passed pawns",
+            EvalTerm::RookOn7th => "Rook on 7th",
+            EvalTerm::KingSafety => "King safety",
+            EvalTerm::QueenSafety => "Queen safety",
+            EvalTerm::QueenAttack => "Queen attack",
+            EvalTerm::Mobility => "Mobility",
+            EvalTerm::Captures => "Captures",
+            EvalTerm::DefendedPieces => "Defended pieces",
+            EvalTerm::KingPawnDistance => "King-pawn distance",
+            EvalTerm::EndgameKingDistance => "Endgame king distance"
+        }
+    }
+}
+
+/// What every evaluation term contributed, each side from its own point of
+/// view, so that a term's total is White minus Black. Terms are shown
+/// tapered by the phase of the position.
+pub struct EvalTrace
+{
+    terms: [[Score; 2]; ALL_TERMS.len()],
+    phase: i32,
+    score: f32
+}
+
+impl EvalTrace
+{
+    pub fn new() -> EvalTrace
+    {
+        EvalTrace
+        {
+            terms: [[Score::ZERO; 2]; ALL_TERMS.len()],
+            phase: TOTAL_PHASE,
+            score: 0.0
+        }
+    }
+
+    /// Records `advantage` for `color` under `term` and returns it white
+    /// relative, to be added to the evaluation.
+    pub fn add(&mut self, term: EvalTerm, color: Color, advantage: Score) -> Score
+    {
+        self.terms[term as usize][color.to_index()] += advantage;
+
+        if color == Color::White { advantage } else { -advantage }
+    }
+
+    pub fn set_phase(&mut self, phase: i32)
+    {
+        self.phase = phase;
+    }
+
+    pub fn set_score(&mut self, score: f32)
+    {
+        self.score = score;
+    }
+}
+
+impl fmt::Display for EvalTrace
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let separator = "-----------------------+--------+--------+--------";
+
+        writeln!(f, " Term                  |  White |  Black |  Total")?;
+        writeln!(f, "{}", separator)?;
+
+        for term in ALL_TERMS
+        {
+            let [white, black] = self.terms[term as usize].map(|score| score.taper(self.phase));
+
+            writeln!(f, " {:<21} | {:>6.2} | {:>6.2} | {:>6.2}", term.name(), white, black, white - black)?;
+        }
+
+        writeln!(f, "{}", separator)?;
+        writeln!(f)?;
+        writeln!(f, "Game phase: {}/{}", self.phase, TOTAL_PHASE)?;
+
+        if self.score.is_infinite()
+        {
+            write!(f, "Final evaluation: {} is checkmated", if self.score > 0.0 { "Black" } else { "White" })
+        }
+        else
+        {
+            write!(f, "Final evaluation: {:+.2} (white side)", self.score)
+        }
+    }
+}
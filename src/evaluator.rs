@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use chess::{Board, ChessMove, MoveGen};
+use crate::consts::PAWN_HASH_SIZE;
+use crate::eval::eval_with_pawn_table;
+use crate::eval_params::EvalParams;
+use crate::nnue::{Network, NnueEval};
+use crate::pawn_hash_table::PawnHashTable;
+use crate::search_options::SearchOptions;
+
+/// A static evaluation the search can use. Backends with incremental state
+/// follow the search through `make_move`/`unmake_move`, every evaluation
+/// still has to be correct for any board it is given.
+pub trait Evaluator
+{
+    /// White relative score in pawns, infinite when the side to move is mated.
+    fn evaluate(&mut self, board: &Board, plies: i32) -> f32;
+
+    /// Called with the root position before a search starts.
+    fn reset(&mut self, _board: &Board) {}
+
+    fn make_move(&mut self, _board: &Board, _mov: ChessMove, _next_board: &Board) {}
+
+    /// Passing the move, undone by `unmake_move` like any other.
+    fn make_null_move(&mut self, _next_board: &Board) {}
+
+    fn unmake_move(&mut self) {}
+}
+
+/// The handcrafted evaluation of `eval.rs`.
+pub struct HandcraftedEval
+{
+    params: Arc<EvalParams>,
+    pawn_table: PawnHashTable
+}
+
+impl HandcraftedEval
+{
+    pub fn new(params: Arc<EvalParams>) -> HandcraftedEval
+    {
+        HandcraftedEval
+        {
+            params,
+            pawn_table: PawnHashTable::new(PAWN_HASH_SIZE)
+        }
+    }
+}
+
+impl Evaluator for HandcraftedEval
+{
+    fn evaluate(&mut self, board: &Board, plies: i32) -> f32
+    {
+        eval_with_pawn_table(board, MoveGen::new_legal(board).collect(), plies, &self.params, &mut self.pawn_table)
+    }
+}
+
+/// The NNUE when a network is loaded, the handcrafted evaluation otherwise.
+pub fn create_evaluator(options: &SearchOptions) -> Box<dyn Evaluator>
+{
+    match &options.network
+    {
+        Some(network) => Box::new(NnueEval::new(network.clone())),
+        None => Box::new(HandcraftedEval::new(options.eval_params.clone()))
+    }
+}
+
+/// Evaluation of a single position by `network`, for the `eval` command.
+pub fn nnue_eval(network: &Arc<Network>, board: &Board) -> f32
+{
+    NnueEval::new(network.clone()).evaluate(board, 0)
+}
@@ -0,0 +1,70 @@
+use chess::{Board, ChessMove, EMPTY};
+use crate::consts::{SWING_EXTENSION, SWING_REDUCTION};
+use crate::move_ordering::is_capture;
+use crate::see::see;
+
+/// Extends moves that swing the material balance by more than
+/// `extension_margin` pawns and reduces those moving it by less than
+/// `reduction_margin`, each at most once along a line. `None` disables
+/// either side.
+#[derive(Clone, Copy)]
+pub struct ExtensionPolicy
+{
+    pub extension_margin: Option<f32>,
+    pub reduction_margin: Option<f32>
+}
+
+impl Default for ExtensionPolicy
+{
+    fn default() -> Self
+    {
+        ExtensionPolicy
+        {
+            extension_margin: Some(SWING_EXTENSION),
+            reduction_margin: Some(SWING_REDUCTION)
+        }
+    }
+}
+
+impl ExtensionPolicy
+{
+    pub fn is_enabled(&self) -> bool
+    {
+        self.extension_margin.is_some() || self.reduction_margin.is_some()
+    }
+
+    /// Depth of a child node and its updated `extended`/`de_extended` flags.
+    pub fn child_depth(&self, depth: u8, swing: f32, extended: bool, de_extended: bool) -> (u8, bool, bool)
+    {
+        if self.extension_margin.is_some_and(|margin| swing > margin) && !extended
+        {
+            (depth, true, de_extended)
+        }
+        else if self.reduction_margin.is_some_and(|margin| swing < margin) && !de_extended && depth > 1
+        {
+            (depth - 2, extended, true)
+        }
+        else
+        {
+            (depth - 1, extended, de_extended)
+        }
+    }
+}
+
+/// Material `mov` wins by the exchange it starts, known from the move alone.
+/// A check swings the game more than any margin.
+pub fn move_swing(board: &Board, mov: ChessMove, next_board: &Board) -> f32
+{
+    if next_board.checkers() != &EMPTY
+    {
+        f32::INFINITY
+    }
+    else if is_capture(board, mov) || mov.get_promotion().is_some()
+    {
+        see(board, mov).max(0.0)
+    }
+    else
+    {
+        0.0
+    }
+}
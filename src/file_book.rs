@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use chess::{Board, ChessMove};
+use serde_json::{Map, Value};
+use crate::move_string_conversion::move_to_string;
+use crate::opening_book::OpeningBook;
+
+#[derive(PartialEq)]
+enum FileFormat
+{
+    Json,
+    Csv
+}
+
+/// Book moves kept in a JSON object (`{"<fen>": "<uci>"}`) or in CSV lines
+/// (`<fen>,<uci>`). Learned moves are written back to the file.
+pub struct FileBook
+{
+    path: PathBuf,
+    format: FileFormat,
+    moves: HashMap<String, String>
+}
+
+impl FileBook
+{
+    /// Reads the book at `path`, a missing file starts an empty book.
+    pub fn open(path: &str) -> Option<FileBook>
+    {
+        let path = PathBuf::from(path);
+
+        let format = match path.extension().and_then(|extension| extension.to_str())
+        {
+            Some("json") => FileFormat::Json,
+            Some("csv") => FileFormat::Csv,
+            _ => return None
+        };
+
+        let content = if path.exists() { fs::read_to_string(&path).ok()? } else { String::new() };
+
+        let moves = match format
+        {
+            FileFormat::Json if content.trim().is_empty() => HashMap::new(),
+            FileFormat::Json => match serde_json::from_str::<Value>(&content).ok()?
+            {
+                Value::Object(map) => map
+                    .into_iter()
+                    .filter_map(|(fen, uci)| uci.as_str().map(|uci| (fen, uci.to_string())))
+                    .collect(),
+                _ => return None
+            },
+            FileFormat::Csv => content
+                .lines()
+                .filter_map(|line| line.rsplit_once(','))
+                .map(|(fen, uci)| (fen.trim().to_string(), uci.trim().to_string()))
+                .collect()
+        };
+
+        Some(FileBook { path, format, moves })
+    }
+
+    fn save(&self, fen: &str, uci: &str)
+    {
+        match self.format
+        {
+            FileFormat::Json =>
+            {
+                let map = self.moves
+                    .iter()
+                    .map(|(fen, uci)| (fen.clone(), Value::String(uci.clone())))
+                    .collect::<Map<String, Value>>();
+
+                if let Ok(content) = serde_json::to_string_pretty(&Value::Object(map))
+                {
+                    let _ = fs::write(&self.path, content);
+                }
+            }
+            FileFormat::Csv =>
+            {
+                if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(&self.path)
+                {
+                    let _ = writeln!(file, "{},{}", fen, uci);
+                }
+            }
+        }
+    }
+}
+
+impl OpeningBook for FileBook
+{
+    fn get_move(&mut self, board: &Board) -> Option<ChessMove>
+    {
+        self.moves
+            .get(&board.to_string())
+            .and_then(|uci| ChessMove::from_str(uci).ok())
+    }
+
+    fn add_move(&mut self, board: &Board, mov: ChessMove)
+    {
+        let fen = board.to_string();
+        let uci = move_to_string(mov);
+
+        if self.moves.get(&fen) != Some(&uci)
+        {
+            self.moves.insert(fen.clone(), uci.clone());
+            self.save(&fen, &uci);
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use chess::{Board, ChessMove, Piece};
+
+#[derive(Clone)]
+struct HistoryEntry
+{
+    hash: u64,
+    halfmove_clock: u32,
+    mov: Option<ChessMove>
+}
+
+/// Zobrist hashes of every position of the game and of the current search
+/// line, with the fifty-move rule counter of each and the move leading to it.
+#[derive(Clone)]
+pub struct GameHistory
+{
+    entries: Vec<HistoryEntry>,
+    start_plies: i32
+}
+
+impl GameHistory
+{
+    pub fn new(board: &Board, halfmove_clock: u32, plies: i32) -> GameHistory
+    {
+        GameHistory
+        {
+            entries: vec![HistoryEntry { hash: board.get_hash(), halfmove_clock, mov: None }],
+            start_plies: plies
+        }
+    }
+
+    /// Plies played since the start of the game.
+    pub fn plies(&self) -> i32
+    {
+        self.start_plies + self.entries.len() as i32 - 1
+    }
+
+    pub fn halfmove_clock(&self) -> u32
+    {
+        self.entries.last().map_or(0, |entry| entry.halfmove_clock)
+    }
+
+    /// Records `next_board`, reached by playing `mov` on `board`.
+    pub fn push(&mut self, board: &Board, mov: ChessMove, next_board: &Board)
+    {
+        let is_zeroing = board.piece_on(mov.get_source()) == Some(Piece::Pawn)
+            || board.piece_on(mov.get_dest()).is_some();
+
+        let halfmove_clock = if is_zeroing { 0 } else { self.halfmove_clock() + 1 };
+
+        self.entries.push(HistoryEntry { hash: next_board.get_hash(), halfmove_clock, mov: Some(mov) });
+    }
+
+    /// Records `next_board`, reached by passing. A repetition can't span a
+    /// null move, so its clock starts over.
+    pub fn push_null(&mut self, next_board: &Board)
+    {
+        self.entries.push(HistoryEntry { hash: next_board.get_hash(), halfmove_clock: 0, mov: None });
+    }
+
+    /// The move that led to the current position, unknown for the first one.
+    pub fn last_move(&self) -> Option<ChessMove>
+    {
+        self.entries.last().and_then(|entry| entry.mov)
+    }
+
+    pub fn pop(&mut self)
+    {
+        self.entries.pop();
+    }
+
+    /// Whether the current position already occurred since the last capture
+    /// or pawn move. Inside the search, after `root_plies`, a single
+    /// repetition is enough to score a draw, the side that could avoid it
+    /// would have repeated again. Positions of the game before it have to
+    /// occur twice, as the threefold rule asks.
+    pub fn is_repetition(&self, root_plies: i32) -> bool
+    {
+        let current = match self.entries.last()
+        {
+            Some(current) => current,
+            None => return false
+        };
+
+        let mut repetitions = 0;
+
+        for (index, entry) in self.entries
+            .iter()
+            .enumerate()
+            .rev()
+            .skip(1)
+            .take(current.halfmove_clock as usize)
+            .skip(1)
+            .step_by(2)
+        {
+            if entry.hash != current.hash
+            {
+                continue;
+            }
+
+            repetitions += 1;
+
+            if self.start_plies + index as i32 > root_plies || repetitions == 2
+            {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn is_fifty_move_draw(&self) -> bool
+    {
+        self.halfmove_clock() >= 100
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    /// Plays `moves` from `board`, returns the final position.
+    fn play(history: &mut GameHistory, mut board: Board, moves: &str) -> Board
+    {
+        for mov in moves.split_whitespace()
+        {
+            let mov = ChessMove::from_str(mov).unwrap();
+            let next_board = board.make_move_new(mov);
+
+            history.push(&board, mov, &next_board);
+            board = next_board;
+        }
+
+        board
+    }
+
+    const KNIGHT_SHUFFLE: &str = "g1f3 g8f6 f3g1 f6g8";
+
+    #[test]
+    fn repetition_inside_the_search_counts_once()
+    {
+        let mut history = GameHistory::new(&Board::default(), 0, 0);
+        let board = play(&mut history, Board::default(), "e2e4 e7e5");
+
+        play(&mut history, board, KNIGHT_SHUFFLE);
+        assert!(history.is_repetition(0));
+    }
+
+    #[test]
+    fn repetition_of_the_game_before_the_root_needs_two()
+    {
+        let mut history = GameHistory::new(&Board::default(), 0, 0);
+        let board = play(&mut history, Board::default(), KNIGHT_SHUFFLE);
+        assert!(!history.is_repetition(4));
+
+        play(&mut history, board, KNIGHT_SHUFFLE);
+        assert!(history.is_repetition(8));
+    }
+
+    #[test]
+    fn capture_or_pawn_move_ends_the_repetition_window()
+    {
+        let mut history = GameHistory::new(&Board::default(), 0, 0);
+        let board = play(&mut history, Board::default(), "g1f3 g8f6 f3g1 f6g8 e2e4");
+
+        assert_eq!(history.halfmove_clock(), 0);
+        play(&mut history, board, "e7e5 g1f3");
+        assert!(!history.is_repetition(0));
+        assert_eq!(history.halfmove_clock(), 1);
+    }
+
+    #[test]
+    fn captures_reset_the_clock()
+    {
+        let board = Board::from_str("4k3/8/8/3p4/8/8/8/3QK3 w - - 12 40").unwrap();
+        let mut history = GameHistory::new(&board, 12, 78);
+
+        let board = play(&mut history, board, "e1e2");
+        assert_eq!(history.halfmove_clock(), 13);
+
+        play(&mut history, board, "e8e7 d1d5");
+        assert_eq!(history.halfmove_clock(), 0);
+        assert_eq!(history.plies(), 81);
+        assert_eq!(history.last_move(), Some(ChessMove::from_str("d1d5").unwrap()));
+    }
+
+    #[test]
+    fn fifty_move_rule()
+    {
+        let board = Board::from_str("4k3/8/8/8/8/8/8/R3K3 w - - 98 80").unwrap();
+        let mut history = GameHistory::new(&board, 98, 158);
+
+        let board = play(&mut history, board, "a1a2");
+        assert!(!history.is_fifty_move_draw());
+
+        play(&mut history, board, "e8e7");
+        assert!(history.is_fifty_move_draw());
+
+        history.pop();
+        assert!(!history.is_fifty_move_draw());
+    }
+}
@@ -1,8 +1,18 @@
 use std::env::args;
+use std::process::exit;
+use crate::bench::run_bench;
+use crate::consts::{BENCH_DEPTH, DATAGEN_DEPTH, EPD_MOVETIME, TUNE_OUTPUT};
+use crate::datagen::{run_datagen, DataFormat};
+use crate::epd::run_epd;
 use crate::info::print_info;
-use crate::uci::start_uci;
+use crate::perft::print_divide;
+use crate::tuner::run_tune;
+use crate::uci::{parse_fen, start_uci};
 
 mod eval;
+mod eval_trace;
+mod eval_params;
+mod score;
 mod search;
 mod uci;
 mod info;
@@ -12,9 +22,35 @@ mod search_move;
 mod promotion_piece;
 mod move_string_conversion;
 mod piece_table;
+mod pawn_structure;
+mod pawn_hash_table;
+mod see;
+mod move_ordering;
 mod transposition_table;
 mod endgame_tablebase;
 mod move_database;
+mod search_limits;
+mod search_context;
+mod search_signals;
+mod search_options;
+mod extension_policy;
+mod pruning_options;
+mod time_manager;
+mod syzygy_tablebase;
+mod shakmaty_conversion;
+mod polyglot_book;
+mod opening_book;
+mod redis_book;
+mod file_book;
+mod book_learning;
+mod game_history;
+mod perft;
+mod bench;
+mod epd;
+mod tuner;
+mod datagen;
+mod evaluator;
+mod nnue;
 
 fn main()
 {
@@ -25,8 +61,84 @@ fn main()
     {
         start_uci()
     }
+    else if arguments[1] == "perft"
+    {
+        run_perft(&arguments[2..])
+    }
+    else if arguments[1] == "bench"
+    {
+        let depth = arguments.get(2).and_then(|depth| depth.parse::<u8>().ok()).unwrap_or(BENCH_DEPTH);
+        run_bench(depth)
+    }
+    else if arguments[1] == "epd" && arguments.len() > 2
+    {
+        let movetime = flag_value(&arguments, "--movetime")
+            .and_then(|movetime| movetime.parse::<u64>().ok())
+            .unwrap_or(EPD_MOVETIME);
+
+        run_epd(&arguments[2], movetime)
+    }
+    else if arguments[1] == "tune" && arguments.len() > 2
+    {
+        let output = flag_value(&arguments, "--output").map_or(TUNE_OUTPUT, |output| output.as_str());
+        let passes = flag_value(&arguments, "--passes").and_then(|passes| passes.parse::<u32>().ok());
+
+        run_tune(&arguments[2], output, passes)
+    }
+    else if arguments[1] == "datagen"
+    {
+        let games = flag_value(&arguments, "--games").and_then(|games| games.parse::<u32>().ok()).unwrap_or(1);
+        let depth = flag_value(&arguments, "--depth").and_then(|depth| depth.parse::<u8>().ok()).unwrap_or(DATAGEN_DEPTH);
+
+        let format = match flag_value(&arguments, "--format").map(|format| format.as_str())
+        {
+            Some("binary") => DataFormat::Binary,
+            _ => DataFormat::Text
+        };
+
+        let default_out = if format == DataFormat::Binary { "datagen.bin" } else { "datagen.txt" };
+        let out = flag_value(&arguments, "--out").map_or(default_out, |out| out.as_str());
+
+        run_datagen(games, depth, out, format)
+    }
     else
     {
         print_info()
     }
 }
+
+/// The argument following `flag`, as in `--movetime 1000`.
+fn flag_value<'a>(arguments: &'a [String], flag: &str) -> Option<&'a String>
+{
+    arguments
+        .iter()
+        .position(|argument| argument == flag)
+        .and_then(|index| arguments.get(index + 1))
+}
+
+/// `perft <fen> <depth>`, the FEN either quoted or as separate arguments.
+fn run_perft(arguments: &[String])
+{
+    let Some((depth, fen)) = arguments.split_last().filter(|(_, fen)| !fen.is_empty())
+    else
+    {
+        eprintln!("usage: Sigma0 perft <fen> <depth>");
+        exit(2);
+    };
+
+    let depth = depth.parse::<u8>().unwrap_or_else(|_|
+    {
+        eprintln!("invalid perft depth {}", depth);
+        exit(2);
+    });
+
+    match parse_fen(&fen.join(" "))
+    {
+        Ok((board, _)) => print_divide(&board, depth),
+        Err(err) =>
+        {
+            eprintln!("{}", err);
+            exit(1);
+        }
+    }
+}
@@ -1,113 +1,61 @@
-use std::{env, io::{self, Write}, str::FromStr};
 use chess::{Board, ChessMove};
-use redis::{Client, Commands, Connection};
-use serde_json::Value;
-use ureq::Agent;
+use crate::opening_book::{open_book, OpeningBook};
+use crate::polyglot_book::PolyglotBook;
 
+/// The engine's opening knowledge: a read-only Polyglot book consulted
+/// first, then the configured `OpeningBook` store, which `BookLearner` fills.
 pub struct MoveDatabase 
 {
-    conn: Option<Connection>,
-    agent: Agent
+    store: Box<dyn OpeningBook>,
+    book: Option<PolyglotBook>,
+    best_book_move: bool
 }
 
 impl MoveDatabase 
 {
+    /// Opens the store chosen by the `OPENING_BOOK` environment variable. A
+    /// Polyglot book can be attached later with `set_book`.
     pub fn load() -> MoveDatabase 
     {
-        let connection = env::var("REDIS_CONNECTION")
-            .unwrap_or_else(|_| panic!("environment variable REDIS_CONNECTION not found."));
-        
-        let client = Client::open(connection);
-
-        if let Ok(client) = client 
-        {
-            MoveDatabase 
-            {
-                conn: Some
-                (
-                    client
-                        .get_connection()
-                        .unwrap()
-                ),
-                agent: Agent::new()
-            }
-        }
-        else 
-        {
-            let mut stdout = io::stdout();
-            let _ = write!(stdout, "info opening book can't be loaded due to network error.");
-
-            MoveDatabase 
-            {
-                conn: None,
-                agent: Agent::new()
-            }
-        }
+        MoveDatabase::with_store(open_book())
     }
 
-    pub fn get_move(&mut self, board: &Board) -> Option<ChessMove>
+    pub fn with_store(store: Box<dyn OpeningBook>) -> MoveDatabase
     {
-        let conn;
-        if self.conn.is_none()
-        {
-            return None;
-        }
-        else 
+        MoveDatabase 
         {
-            conn = self.conn.as_mut().unwrap();
+            store,
+            book: None,
+            best_book_move: false
         }
+    }
 
-        let board_fen = board.to_string();
-        let uci = conn.get(board_fen)
-            .map_or(None, |uci: String| Some(uci));
-
-        if let Some(uci) = uci 
-        {
-            Some(ChessMove::from_str(&uci).unwrap())
-        }
-        else 
-        {
-            None
-        }
+    pub fn set_book(&mut self, book: Option<PolyglotBook>, best_book_move: bool)
+    {
+        self.book = book;
+        self.best_book_move = best_book_move;
     }
+}
 
-    pub fn add_move(&mut self, board: &Board) 
+impl OpeningBook for MoveDatabase
+{
+    fn get_move(&mut self, board: &Board) -> Option<ChessMove>
     {
-        let conn;
-        if self.conn.is_none()
+        if let Some(mov) = self.book.as_ref().and_then(|book| book.get_move(board, self.best_book_move))
         {
-            return ()
+            return Some(mov);
         }
-        else 
-        {
-            conn = self.conn.as_mut().unwrap();
-        }
-
-        let board_fen = board.to_string();
-        let uri = format!("https://stockfish.online/api/s/v2.php?fen={board_fen}&depth=15");
-        let stockfish_move = self.agent.get(&uri)
-            .call()
-            .unwrap()
-            .into_json::<Value>()
-            .unwrap();
-
-        match stockfish_move 
-        {
-            Value::Object(map) => 
-            {
-                let str = map["bestmove"]
-                    .to_string();
 
-                let spl = str
-                    .split_whitespace()
-                    .collect::<Vec<&str>>();
+        self.store.get_move(board)
+    }
 
-                let uci_move = spl[1].to_string();
-                let _ = conn.set::<String, String, String>(board_fen, uci_move);
-            },
-            _ => {}
-        }
+    fn add_move(&mut self, board: &Board, mov: ChessMove)
+    {
+        self.store.add_move(board, mov);
+    }
 
+    fn is_read_only(&self) -> bool
+    {
+        self.store.is_read_only()
     }
 }
-
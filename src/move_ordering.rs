@@ -0,0 +1,232 @@
+use chess::{BitBoard, Board, ChessMove, MoveGen, Piece, Square};
+use crate::consts::MAX_HISTORY;
+use crate::see::see;
+
+const TT_MOVE_SCORE: i32 = 1_000_000;
+const GOOD_CAPTURE_SCORE: i32 = 500_000;
+const PROMOTION_SCORE: i32 = 450_000;
+const KILLER_SCORES: [i32; 2] = [400_000, 390_000];
+const COUNTERMOVE_SCORE: i32 = 300_000;
+const BAD_CAPTURE_SCORE: i32 = -100_000;
+
+/// Quiet moves that caused beta cutoffs, remembered across the search of one
+/// position to order the moves of every node without evaluating them.
+pub struct MoveOrdering
+{
+    /// Two quiet moves per ply from the root.
+    killers: Vec<[Option<ChessMove>; 2]>,
+    /// Butterfly table, by side to move, source and destination.
+    history: Vec<i32>,
+    /// Refutation of each move, by its source and destination.
+    countermoves: Vec<Option<ChessMove>>
+}
+
+fn square_pair(mov: ChessMove) -> usize
+{
+    mov.get_source().to_index() * 64 + mov.get_dest().to_index()
+}
+
+fn history_index(board: &Board, mov: ChessMove) -> usize
+{
+    board.side_to_move().to_index() * 64 * 64 + square_pair(mov)
+}
+
+/// Most valuable victim first, among equal victims least valuable attacker
+/// first.
+fn mvv_lva(board: &Board, mov: ChessMove) -> i32
+{
+    let victim = board.piece_on(mov.get_dest()).unwrap_or(Piece::Pawn);
+    let attacker = board.piece_on(mov.get_source()).unwrap_or(Piece::Pawn);
+
+    (victim.to_index() as i32 + 1) * 8 - attacker.to_index() as i32
+}
+
+/// Whether `mov` takes a piece, en passant included.
+pub fn is_capture(board: &Board, mov: ChessMove) -> bool
+{
+    board.piece_on(mov.get_dest()).is_some() || Some(mov.get_dest()) == en_passant_target(board)
+}
+
+/// The square a pawn lands on when capturing en passant.
+fn en_passant_target(board: &Board) -> Option<Square>
+{
+    board.en_passant().and_then(|square| square.forward(board.side_to_move()))
+}
+
+/// The captures that don't lose material, most valuable victim first. Only
+/// captures are generated and each is exchanged out once, for the
+/// quiescence search.
+pub fn good_captures(board: &Board) -> Vec<ChessMove>
+{
+    let mut targets = *board.color_combined(!board.side_to_move());
+
+    if let Some(square) = en_passant_target(board)
+    {
+        targets |= BitBoard::from_square(square);
+    }
+
+    let mut moves = MoveGen::new_legal(board);
+    moves.set_iterator_mask(targets);
+
+    let mut captures: Vec<ChessMove> = moves.filter(|mov| see(board, *mov) >= 0.0).collect();
+
+    captures.sort_by_key(|mov| -mvv_lva(board, *mov));
+    captures
+}
+
+impl MoveOrdering
+{
+    pub fn new() -> MoveOrdering
+    {
+        MoveOrdering
+        {
+            killers: Vec::new(),
+            history: vec![0; 2 * 64 * 64],
+            countermoves: vec![None; 64 * 64]
+        }
+    }
+
+    fn killers(&self, ply: u32) -> [Option<ChessMove>; 2]
+    {
+        self.killers.get(ply as usize).copied().unwrap_or([None; 2])
+    }
+
+    fn score(&self, board: &Board, mov: ChessMove, tt_move: Option<ChessMove>, ply: u32, previous: Option<ChessMove>) -> i32
+    {
+        if Some(mov) == tt_move
+        {
+            return TT_MOVE_SCORE;
+        }
+
+        if is_capture(board, mov)
+        {
+            let base = if see(board, mov) >= 0.0 { GOOD_CAPTURE_SCORE } else { BAD_CAPTURE_SCORE };
+            return base + mvv_lva(board, mov);
+        }
+
+        if mov.get_promotion() == Some(Piece::Queen)
+        {
+            return PROMOTION_SCORE;
+        }
+
+        if let Some(slot) = self.killers(ply).iter().position(|killer| *killer == Some(mov))
+        {
+            return KILLER_SCORES[slot];
+        }
+
+        if previous.is_some_and(|previous| self.countermoves[square_pair(previous)] == Some(mov))
+        {
+            return COUNTERMOVE_SCORE;
+        }
+
+        self.history[history_index(board, mov)]
+    }
+
+    /// `moves` best first: the transposition table move, captures that
+    /// don't lose material, queen promotions, killers, the countermove of
+    /// `previous`, the other quiet moves by history and losing captures.
+    pub fn order(&self, board: &Board, moves: Vec<ChessMove>, tt_move: Option<ChessMove>, ply: u32, previous: Option<ChessMove>) -> Vec<ChessMove>
+    {
+        let mut scored: Vec<(ChessMove, i32)> = moves
+            .into_iter()
+            .map(|mov| (mov, self.score(board, mov, tt_move, ply, previous)))
+            .collect();
+
+        scored.sort_by_key(|(_, score)| -score);
+        scored.into_iter().map(|(mov, _)| mov).collect()
+    }
+
+    /// Records the quiet `mov` that failed high at `depth`, after the quiet
+    /// moves of `tried` failed to.
+    pub fn update(&mut self, board: &Board, mov: ChessMove, depth: u8, ply: u32, previous: Option<ChessMove>, tried: &[ChessMove])
+    {
+        let ply = ply as usize;
+
+        if self.killers.len() <= ply
+        {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+
+        if self.killers[ply][0] != Some(mov)
+        {
+            self.killers[ply] = [Some(mov), self.killers[ply][0]];
+        }
+
+        if let Some(previous) = previous
+        {
+            self.countermoves[square_pair(previous)] = Some(mov);
+        }
+
+        let bonus = (depth as i32 * depth as i32).min(MAX_HISTORY);
+
+        self.add_history(history_index(board, mov), bonus);
+
+        for tried in tried
+        {
+            self.add_history(history_index(board, *tried), -bonus);
+        }
+    }
+
+    /// Moves the entry towards `bonus` by an amount shrinking as it nears
+    /// `MAX_HISTORY`, so that the table never saturates.
+    fn add_history(&mut self, index: usize, bonus: i32)
+    {
+        let entry = &mut self.history[index];
+        *entry += bonus - *entry * bonus.abs() / MAX_HISTORY;
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    fn mov(text: &str) -> ChessMove
+    {
+        ChessMove::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn tt_move_then_good_captures_then_killers()
+    {
+        let board = Board::from_str("r1bqkbnr/pppp1ppp/2n5/4p3/3PP3/8/PPP2PPP/RNBQKBNR w KQkq - 1 3").unwrap();
+        let mut ordering = MoveOrdering::new();
+
+        ordering.update(&board, mov("g1f3"), 4, 2, None, &[mov("b1c3")]);
+
+        let moves = ordering.order(&board, MoveGen::new_legal(&board).collect(), Some(mov("a2a3")), 2, None);
+
+        assert_eq!(moves[..3], [mov("a2a3"), mov("d4e5"), mov("g1f3")]);
+
+        // The quiet move that failed to cut off sorts below the other quiet moves
+        assert_eq!(moves.last(), Some(&mov("b1c3")));
+    }
+
+    #[test]
+    fn losing_captures_come_after_quiet_moves()
+    {
+        let board = Board::from_str("4k3/8/2p5/3n4/8/8/8/3RK3 w - - 0 1").unwrap();
+        let moves = MoveOrdering::new().order(&board, MoveGen::new_legal(&board).collect(), None, 0, None);
+
+        assert_eq!(moves.last(), Some(&mov("d1d5")));
+    }
+
+    #[test]
+    fn en_passant_is_a_good_capture()
+    {
+        let board = Board::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+
+        assert!(is_capture(&board, mov("e5d6")));
+        assert!(!is_capture(&board, mov("e5e6")));
+        assert_eq!(good_captures(&board), [mov("e5d6")]);
+    }
+
+    #[test]
+    fn good_captures_skip_losing_ones()
+    {
+        let board = Board::from_str("4k3/8/2p5/3n4/4P3/8/8/3RK3 w - - 0 1").unwrap();
+
+        assert_eq!(good_captures(&board), [mov("e4d5")]);
+    }
+}
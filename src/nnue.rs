@@ -0,0 +1,458 @@
+use std::fs;
+use std::sync::Arc;
+use chess::{Board, BoardStatus, ChessMove, Color, File, Piece, Square, ALL_SQUARES};
+use crate::evaluator::Evaluator;
+
+const MAGIC: &[u8; 4] = b"SGNN";
+const FEATURES: usize = 64 * 12 * 64;
+
+/// Clipped ReLU ceiling of the accumulator, output weight scale and the
+/// centipawn scale of the output.
+const QA: i32 = 255;
+const QB: i32 = 64;
+const SCALE: i32 = 400;
+
+/// A HalfKA network: for each side, one feature per (own king square, piece
+/// colour and type, piece square) seen from that side's point of view, a
+/// hidden layer of any size and a single output fed by both sides'
+/// accumulators, the side to move's first.
+///
+/// File layout, little endian: `SGNN`, the hidden size as u32, the feature
+/// weights as i16 feature by feature, the hidden biases as i16, the output
+/// weights as i16 and the output bias as i32.
+pub struct Network
+{
+    hidden: usize,
+    feature_weights: Vec<i16>,
+    feature_biases: Vec<i16>,
+    output_weights: Vec<i16>,
+    output_bias: i32
+}
+
+fn read_i16s(bytes: &[u8]) -> Vec<i16>
+{
+    bytes.chunks_exact(2).map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]])).collect()
+}
+
+impl Network
+{
+    pub fn load(path: &str) -> Result<Network, String>
+    {
+        let bytes = fs::read(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+
+        if bytes.len() < 8 || &bytes[..4] != MAGIC
+        {
+            return Err(format!("{} is not a Sigma0 network", path));
+        }
+
+        let hidden = u32::from_le_bytes(bytes[4..8].try_into().unwrap()) as usize;
+        let weights_end = 8 + FEATURES * hidden * 2;
+        let biases_end = weights_end + hidden * 2;
+        let output_end = biases_end + 2 * hidden * 2;
+
+        if hidden == 0 || bytes.len() != output_end + 4
+        {
+            return Err(format!("{} has the wrong size for a hidden layer of {}", path, hidden));
+        }
+
+        Ok(Network
+        {
+            hidden,
+            feature_weights: read_i16s(&bytes[8..weights_end]),
+            feature_biases: read_i16s(&bytes[weights_end..biases_end]),
+            output_weights: read_i16s(&bytes[biases_end..output_end]),
+            output_bias: i32::from_le_bytes(bytes[output_end..].try_into().unwrap())
+        })
+    }
+
+    pub fn hidden(&self) -> usize
+    {
+        self.hidden
+    }
+
+    fn weights(&self, feature: usize) -> &[i16]
+    {
+        &self.feature_weights[feature * self.hidden..(feature + 1) * self.hidden]
+    }
+}
+
+/// Index of the feature of `piece` of `color` on `square` for `perspective`,
+/// Black's side seeing the board flipped.
+fn feature(perspective: Color, king: Square, piece: Piece, color: Color, square: Square) -> usize
+{
+    let orient = |square: Square| if perspective == Color::White { square.to_index() } else { square.to_index() ^ 56 };
+    let relation = if color == perspective { 0 } else { 6 };
+
+    orient(king) * 768 + (relation + piece.to_index()) * 64 + orient(square)
+}
+
+/// Hidden layer values of both sides for one position, indexed by colour.
+#[derive(Clone)]
+struct Accumulator
+{
+    values: [Vec<i16>; 2],
+    hash: u64
+}
+
+pub struct NnueEval
+{
+    network: Arc<Network>,
+    stack: Vec<Accumulator>,
+    top: usize
+}
+
+impl NnueEval
+{
+    pub fn new(network: Arc<Network>) -> NnueEval
+    {
+        let empty = Accumulator { values: [Vec::new(), Vec::new()], hash: 0 };
+
+        NnueEval
+        {
+            network,
+            stack: vec![empty],
+            top: 0
+        }
+    }
+
+    fn refresh(network: &Network, values: &mut Vec<i16>, perspective: Color, board: &Board)
+    {
+        values.clear();
+        values.extend_from_slice(&network.feature_biases);
+
+        let king = board.king_square(perspective);
+
+        for square in ALL_SQUARES
+        {
+            if let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square))
+            {
+                let weights = network.weights(feature(perspective, king, piece, color, square));
+                values.iter_mut().zip(weights).for_each(|(value, weight)| *value = value.wrapping_add(*weight));
+            }
+        }
+    }
+
+    fn refresh_top(&mut self, board: &Board)
+    {
+        let accumulator = &mut self.stack[self.top];
+
+        for perspective in [Color::White, Color::Black]
+        {
+            NnueEval::refresh(&self.network, &mut accumulator.values[perspective.to_index()], perspective, board);
+        }
+
+        accumulator.hash = board.get_hash();
+    }
+
+    fn output(&self, side_to_move: Color) -> i32
+    {
+        let network = &self.network;
+        let accumulator = &self.stack[self.top];
+
+        let us = &accumulator.values[side_to_move.to_index()];
+        let them = &accumulator.values[(!side_to_move).to_index()];
+        let (us_weights, them_weights) = network.output_weights.split_at(network.hidden);
+
+        // A wide hidden layer overflows i32 before the division brings the
+        // sum back into range
+        let sum = |values: &[i16], weights: &[i16]| values
+            .iter()
+            .zip(weights)
+            .map(|(value, weight)| (*value as i64).clamp(0, QA as i64) * *weight as i64)
+            .sum::<i64>();
+
+        let output = (sum(us, us_weights) + sum(them, them_weights) + network.output_bias as i64) * SCALE as i64 / (QA * QB) as i64;
+
+        output.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+    }
+}
+
+impl Evaluator for NnueEval
+{
+    fn evaluate(&mut self, board: &Board, _plies: i32) -> f32
+    {
+        match board.status()
+        {
+            BoardStatus::Checkmate => return if board.side_to_move() == Color::White { f32::NEG_INFINITY } else { f32::INFINITY },
+            BoardStatus::Stalemate => return 0.0,
+            BoardStatus::Ongoing => {}
+        }
+
+        // Positions reached without `make_move` are computed from scratch
+        if self.stack[self.top].hash != board.get_hash() || self.stack[self.top].values[0].is_empty()
+        {
+            self.refresh_top(board);
+        }
+
+        let score = self.output(board.side_to_move()) as f32 / 100.0;
+
+        if board.side_to_move() == Color::White { score } else { -score }
+    }
+
+    fn reset(&mut self, board: &Board)
+    {
+        self.top = 0;
+        self.refresh_top(board);
+    }
+
+    /// Adds and removes the features of the pieces `mov` touches. A king
+    /// move changes every feature of its own side, which is rebuilt.
+    fn make_move(&mut self, board: &Board, mov: ChessMove, next_board: &Board)
+    {
+        if self.stack.len() == self.top + 1
+        {
+            self.stack.push(self.stack[self.top].clone());
+        }
+
+        let (done, rest) = self.stack.split_at_mut(self.top + 1);
+        let (parent, child) = (&done[self.top], &mut rest[0]);
+        self.top += 1;
+
+        let us = board.side_to_move();
+        let (source, dest) = (mov.get_source(), mov.get_dest());
+        let piece = board.piece_on(source).unwrap_or(Piece::Pawn);
+
+        let mut removed = vec![(piece, us, source)];
+        let mut added = vec![(mov.get_promotion().unwrap_or(piece), us, dest)];
+
+        if let Some(captured) = board.piece_on(dest)
+        {
+            removed.push((captured, !us, dest));
+        }
+        else if piece == Piece::Pawn && source.get_file() != dest.get_file()
+        {
+            removed.push((Piece::Pawn, !us, Square::make_square(source.get_rank(), dest.get_file())));
+        }
+
+        if piece == Piece::King && source.get_file().to_index().abs_diff(dest.get_file().to_index()) == 2
+        {
+            let (rook_from, rook_to) = if dest.get_file() == File::G { (File::H, File::F) } else { (File::A, File::D) };
+
+            removed.push((Piece::Rook, us, Square::make_square(source.get_rank(), rook_from)));
+            added.push((Piece::Rook, us, Square::make_square(source.get_rank(), rook_to)));
+        }
+
+        for perspective in [Color::White, Color::Black]
+        {
+            let values = &mut child.values[perspective.to_index()];
+
+            if piece == Piece::King && perspective == us || parent.values[0].is_empty()
+            {
+                NnueEval::refresh(&self.network, values, perspective, next_board);
+                continue;
+            }
+
+            values.clear();
+            values.extend_from_slice(&parent.values[perspective.to_index()]);
+
+            let king = next_board.king_square(perspective);
+
+            for (piece, color, square) in &removed
+            {
+                let weights = self.network.weights(feature(perspective, king, *piece, *color, *square));
+                values.iter_mut().zip(weights).for_each(|(value, weight)| *value = value.wrapping_sub(*weight));
+            }
+
+            for (piece, color, square) in &added
+            {
+                let weights = self.network.weights(feature(perspective, king, *piece, *color, *square));
+                values.iter_mut().zip(weights).for_each(|(value, weight)| *value = value.wrapping_add(*weight));
+            }
+        }
+
+        child.hash = next_board.get_hash();
+    }
+
+    /// Passing leaves every piece in place, only the side to move changes.
+    fn make_null_move(&mut self, next_board: &Board)
+    {
+        if self.stack.len() == self.top + 1
+        {
+            self.stack.push(self.stack[self.top].clone());
+        }
+        else
+        {
+            self.stack[self.top + 1] = self.stack[self.top].clone();
+        }
+
+        self.top += 1;
+        self.stack[self.top].hash = next_board.get_hash();
+    }
+
+    fn unmake_move(&mut self)
+    {
+        self.top = self.top.saturating_sub(1);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use chess::MoveGen;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+    use super::*;
+
+    /// A network of full range random weights, so that the accumulators
+    /// also wrap around.
+    fn random_network(rng: &mut StdRng) -> Arc<Network>
+    {
+        let hidden = 8;
+
+        Arc::new(Network
+        {
+            hidden,
+            feature_weights: (0..FEATURES * hidden).map(|_| rng.gen()).collect(),
+            feature_biases: (0..hidden).map(|_| rng.gen()).collect(),
+            output_weights: (0..2 * hidden).map(|_| rng.gen()).collect(),
+            output_bias: rng.gen_range(-1000..1000)
+        })
+    }
+
+    fn assert_matches_refresh(eval: &NnueEval, board: &Board)
+    {
+        for perspective in [Color::White, Color::Black]
+        {
+            let mut values = Vec::new();
+            NnueEval::refresh(&eval.network, &mut values, perspective, board);
+
+            assert_eq!(eval.stack[eval.top].values[perspective.to_index()], values, "{} from {:?}", board, perspective);
+        }
+    }
+
+    /// Plays `moves` from `fen` and checks every accumulator on the way, and
+    /// again after taking the moves back.
+    fn check_line(eval: &mut NnueEval, fen: &str, moves: &[ChessMove])
+    {
+        let mut boards = vec![Board::from_str(fen).unwrap()];
+        eval.reset(&boards[0]);
+
+        for &mov in moves
+        {
+            let board = *boards.last().unwrap();
+            assert!(board.legal(mov), "{} is illegal in {}", mov, board);
+
+            let next_board = board.make_move_new(mov);
+            eval.make_move(&board, mov, &next_board);
+            assert_matches_refresh(eval, &next_board);
+
+            boards.push(next_board);
+        }
+
+        for board in boards.iter().rev().skip(1)
+        {
+            eval.unmake_move();
+            assert_matches_refresh(eval, board);
+        }
+    }
+
+    /// The bytes of a network file with a hidden layer of `hidden`.
+    fn network_file(hidden: usize) -> Vec<u8>
+    {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend((hidden as u32).to_le_bytes());
+
+        let weights = FEATURES * hidden + hidden + 2 * hidden;
+        bytes.extend((0..weights).flat_map(|index| ((index % 2001) as i16 - 1000).to_le_bytes()));
+        bytes.extend((-1234i32).to_le_bytes());
+
+        bytes
+    }
+
+    fn load_bytes(name: &str, bytes: &[u8]) -> Result<Network, String>
+    {
+        let path = std::env::temp_dir().join(format!("sigma0-{}-{}.nnue", name, std::process::id()));
+        fs::write(&path, bytes).unwrap();
+
+        let network = Network::load(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+
+        network
+    }
+
+    #[test]
+    fn network_file_loads()
+    {
+        let network = load_bytes("valid", &network_file(2)).unwrap();
+        let value = |index: usize| (index % 2001) as i16 - 1000;
+
+        assert_eq!(network.hidden(), 2);
+        assert_eq!(network.feature_weights.len(), FEATURES * 2);
+        assert_eq!(network.weights(1), &[value(2), value(3)]);
+        assert_eq!(network.feature_biases, [value(FEATURES * 2), value(FEATURES * 2 + 1)]);
+        assert_eq!(network.output_weights, (FEATURES * 2 + 2..FEATURES * 2 + 6).map(value).collect::<Vec<i16>>());
+        assert_eq!(network.output_bias, -1234);
+    }
+
+    #[test]
+    fn bad_network_files_are_rejected()
+    {
+        let mut bad_magic = network_file(1);
+        bad_magic[0] = b'X';
+        assert!(load_bytes("magic", &bad_magic).is_err());
+
+        let file = network_file(1);
+        assert!(load_bytes("short", &file[..file.len() - 1]).is_err());
+        assert!(load_bytes("long", &[file.as_slice(), &[0]].concat()).is_err());
+        assert!(load_bytes("header", &file[..6]).is_err());
+
+        let mut wrong_size = file.clone();
+        wrong_size[4] = 2;
+        assert!(load_bytes("size", &wrong_size).is_err());
+        assert!(load_bytes("empty", &network_file(0)).is_err());
+    }
+
+    #[test]
+    fn wide_output_does_not_overflow()
+    {
+        let hidden = 300;
+        let network = Arc::new(Network
+        {
+            hidden,
+            feature_weights: vec![0; FEATURES * hidden],
+            feature_biases: vec![QA as i16; hidden],
+            output_weights: vec![i16::MAX; 2 * hidden],
+            output_bias: 0
+        });
+
+        let mut eval = NnueEval::new(network);
+        eval.reset(&Board::default());
+
+        let expected = 2 * hidden as i64 * QA as i64 * i16::MAX as i64 * SCALE as i64 / (QA * QB) as i64;
+        assert_eq!(eval.output(Color::White) as i64, expected);
+    }
+
+    #[test]
+    fn incremental_updates_match_refresh()
+    {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut eval = NnueEval::new(random_network(&mut rng));
+
+        // En passant, capturing promotion, castling on both sides and king moves
+        let line = ["d5e6", "e8g8", "b7a8q", "f8a8", "e1c1", "g8h8", "c1b1", "a7a5"];
+        let fen = "r3k2r/pPpp1ppp/8/3Pp3/8/8/P1PP1PPP/R3K2R w KQkq e6 0 1";
+
+        check_line(&mut eval, fen, &line.map(|mov| ChessMove::from_str(mov).unwrap()));
+
+        for fen in [fen, "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "8/2P2k2/8/3pP3/8/8/5Kp1/8 w - d6 0 1"]
+        {
+            for _ in 0..20
+            {
+                let mut board = Board::from_str(fen).unwrap();
+                let mut moves = Vec::new();
+
+                while moves.len() < 40
+                {
+                    let Some(&mov) = MoveGen::new_legal(&board).collect::<Vec<_>>().choose(&mut rng) else { break };
+
+                    board = board.make_move_new(mov);
+                    moves.push(mov);
+                }
+
+                check_line(&mut eval, fen, &moves);
+            }
+        }
+    }
+}
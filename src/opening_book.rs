@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+use chess::{Board, ChessMove};
+use crate::file_book::FileBook;
+use crate::move_string_conversion::move_to_string;
+use crate::redis_book::RedisBook;
+
+/// A store of book moves keyed by position. `MoveDatabase` reads from it
+/// during the opening and writes learned moves back into it.
+pub trait OpeningBook: Send
+{
+    fn get_move(&mut self, board: &Board) -> Option<ChessMove>;
+
+    fn add_move(&mut self, board: &Board, mov: ChessMove);
+
+    /// Books that never keep anything are skipped by learning.
+    fn is_read_only(&self) -> bool
+    {
+        false
+    }
+}
+
+/// Used when no book is configured.
+pub struct NoBook;
+
+impl OpeningBook for NoBook
+{
+    fn get_move(&mut self, _board: &Board) -> Option<ChessMove>
+    {
+        None
+    }
+
+    fn add_move(&mut self, _board: &Board, _mov: ChessMove) {}
+
+    fn is_read_only(&self) -> bool
+    {
+        true
+    }
+}
+
+/// Keeps book moves for the lifetime of the process only.
+pub struct MemoryBook
+{
+    moves: HashMap<String, String>
+}
+
+impl MemoryBook
+{
+    pub fn new() -> MemoryBook
+    {
+        MemoryBook
+        {
+            moves: HashMap::new()
+        }
+    }
+}
+
+impl OpeningBook for MemoryBook
+{
+    fn get_move(&mut self, board: &Board) -> Option<ChessMove>
+    {
+        self.moves
+            .get(&board.to_string())
+            .and_then(|uci| ChessMove::from_str(uci).ok())
+    }
+
+    fn add_move(&mut self, board: &Board, mov: ChessMove)
+    {
+        self.moves.insert(board.to_string(), move_to_string(mov));
+    }
+}
+
+/// Opens the book named by `OPENING_BOOK`: `none`, `memory`, `redis` (at
+/// `REDIS_CONNECTION`) or the path of a `.json` or `.csv` file. Without the
+/// variable, Redis is used if `REDIS_CONNECTION` is set.
+pub fn open_book() -> Box<dyn OpeningBook>
+{
+    let spec = env::var("OPENING_BOOK").unwrap_or_else(|_|
+    {
+        if env::var("REDIS_CONNECTION").is_ok() { "redis".to_string() } else { "none".to_string() }
+    });
+
+    let book: Option<Box<dyn OpeningBook>> = match spec.as_str()
+    {
+        "none" => Some(Box::new(NoBook)),
+        "memory" => Some(Box::new(MemoryBook::new())),
+        "redis" => RedisBook::connect().map(|book| Box::new(book) as Box<dyn OpeningBook>),
+        path => FileBook::open(path).map(|book| Box::new(book) as Box<dyn OpeningBook>)
+    };
+
+    book.unwrap_or_else(||
+    {
+        println!("info string opening book {} can't be loaded.", spec);
+        Box::new(NoBook)
+    })
+}
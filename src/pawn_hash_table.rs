@@ -0,0 +1,39 @@
+use chess::Board;
+use crate::eval_params::EvalParams;
+use crate::pawn_structure::{pawn_key, PawnEntry};
+
+/// Pawn structures already evaluated, replaced on collision. Each evaluator
+/// owns one, so its weights never change under it.
+pub struct PawnHashTable
+{
+    entries: Vec<Option<PawnEntry>>
+}
+
+impl PawnHashTable
+{
+    /// `size` entries, rounded up to a power of two.
+    pub fn new(size: usize) -> PawnHashTable
+    {
+        PawnHashTable
+        {
+            entries: vec![None; size.next_power_of_two()]
+        }
+    }
+
+    pub fn probe(&mut self, board: &Board, params: &EvalParams) -> PawnEntry
+    {
+        let key = pawn_key(board);
+        let index = key as usize & (self.entries.len() - 1);
+
+        match self.entries[index]
+        {
+            Some(entry) if entry.key == key => entry,
+            _ =>
+            {
+                let entry = PawnEntry::new(board, key, params);
+                self.entries[index] = Some(entry);
+                entry
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use chess::{get_adjacent_files, get_file, get_pawn_attacks, get_rank, BitBoard, Board, Color, Piece, Rank, Square, EMPTY};
+use shakmaty::zobrist::{ZobristValue, Zobrist64};
+use crate::eval_params::EvalParams;
+use crate::score::Score;
+use crate::shakmaty_conversion::{shakmaty_piece, shakmaty_square};
+
+/// Zobrist key of the pawns alone, all the pawn structure depends on.
+pub fn pawn_key(board: &Board) -> u64
+{
+    let mut key = Zobrist64::default();
+
+    for color in [Color::White, Color::Black]
+    {
+        for square in board.pieces(Piece::Pawn) & board.color_combined(color)
+        {
+            key ^= Zobrist64::zobrist_for_piece(shakmaty_square(square), shakmaty_piece(Piece::Pawn, color));
+        }
+    }
+
+    key.0
+}
+
+/// Every rank in front of `square` as seen by `color`.
+fn ranks_ahead(square: Square, color: Color) -> BitBoard
+{
+    let rank = square.get_rank().to_index();
+    let ranks = match color
+    {
+        Color::White => rank + 1..8,
+        Color::Black => 0..rank
+    };
+
+    ranks.fold(EMPTY, |mask, rank| mask | get_rank(Rank::from_index(rank)))
+}
+
+/// Rank of `square` counted from `color`'s side, 1 for a pawn on its
+/// starting square.
+fn relative_rank(square: Square, color: Color) -> usize
+{
+    match color
+    {
+        Color::White => square.get_rank().to_index(),
+        Color::Black => 7 - square.get_rank().to_index()
+    }
+}
+
+/// The part of the pawn evaluation that only depends on where the pawns
+/// stand, which is what the pawn hash table keeps.
+#[derive(Clone, Copy)]
+pub struct PawnEntry
+{
+    pub key: u64,
+    /// Doubled, isolated, backward and connected pawns of each side, from
+    /// its own point of view.
+    pub structure: [Score; 2],
+    pub passed: [BitBoard; 2]
+}
+
+impl PawnEntry
+{
+    pub fn new(board: &Board, key: u64, params: &EvalParams) -> PawnEntry
+    {
+        let pawns = *board.pieces(Piece::Pawn);
+        let mut entry = PawnEntry { key, structure: [Score::ZERO; 2], passed: [EMPTY; 2] };
+
+        for color in [Color::White, Color::Black]
+        {
+            let ours = pawns & board.color_combined(color);
+            let theirs = pawns & board.color_combined(!color);
+            let structure = &mut entry.structure[color.to_index()];
+
+            for square in ours
+            {
+                let file = get_file(square.get_file());
+                let adjacent = get_adjacent_files(square.get_file());
+                let ahead = ranks_ahead(square, color);
+
+                // Pawns of ours defending the square are the ones an enemy
+                // pawn standing there would attack
+                let supporters = get_pawn_attacks(square, !color, ours);
+                let phalanx = ours & adjacent & get_rank(square.get_rank());
+
+                if ours & file & ahead != EMPTY
+                {
+                    *structure += -params.doubled_pawn;
+                }
+
+                if ours & adjacent == EMPTY
+                {
+                    *structure += -params.isolated_pawn;
+                }
+                else if ours & adjacent & !ahead == EMPTY
+                {
+                    let stop_attacked = square
+                        .forward(color)
+                        .is_some_and(|stop| get_pawn_attacks(stop, color, theirs) != EMPTY);
+
+                    if stop_attacked
+                    {
+                        *structure += -params.backward_pawn;
+                    }
+                }
+
+                if supporters | phalanx != EMPTY
+                {
+                    *structure += params.connected_pawn;
+                }
+
+                if (theirs | (ours & file)) & (file | adjacent) & ahead == EMPTY
+                {
+                    entry.passed[color.to_index()] |= BitBoard::from_square(square);
+                }
+            }
+        }
+
+        entry
+    }
+}
+
+/// Bonus of the passed pawn of `color` on `square`, growing with the square
+/// of its rank and larger when nothing stands on its way to promotion.
+pub fn passed_pawn_bonus(board: &Board, square: Square, color: Color, params: &EvalParams) -> Score
+{
+    let rank = relative_rank(square, color);
+    let scale = (rank * rank) as f32;
+    let path = get_file(square.get_file()) & ranks_ahead(square, color);
+
+    if board.combined() & path == EMPTY
+    {
+        (params.passed_pawn + params.passed_pawn_free_path) * scale
+    }
+    else
+    {
+        params.passed_pawn * scale
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    fn pawn_entry(fen: &str) -> (Board, PawnEntry)
+    {
+        let board = Board::from_str(fen).unwrap();
+        let entry = PawnEntry::new(&board, pawn_key(&board), &EvalParams::default());
+
+        (board, entry)
+    }
+
+    fn squares(bitboard: BitBoard) -> Vec<Square>
+    {
+        bitboard.collect()
+    }
+
+    #[test]
+    fn doubled_and_isolated_pawns()
+    {
+        let params = EvalParams::default();
+        let (_, entry) = pawn_entry("4k3/8/8/8/8/4P3/4P3/4K3 w - - 0 1");
+
+        assert_eq!(entry.structure[Color::White.to_index()], -params.isolated_pawn * 2.0 - params.doubled_pawn);
+        assert_eq!(entry.structure[Color::Black.to_index()], Score::ZERO);
+
+        // The rear pawn is blocked by its own
+        assert_eq!(squares(entry.passed[Color::White.to_index()]), [Square::E3]);
+    }
+
+    #[test]
+    fn backward_and_connected_pawns()
+    {
+        let params = EvalParams::default();
+        let (_, entry) = pawn_entry("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1");
+
+        // d3 is behind its neighbour with d4 watched by c5, e4 is defended by d3
+        assert_eq!(entry.structure[Color::White.to_index()], -params.backward_pawn + params.connected_pawn);
+        assert_eq!(entry.structure[Color::Black.to_index()], -params.isolated_pawn);
+    }
+
+    #[test]
+    fn passed_pawns()
+    {
+        let (_, entry) = pawn_entry("4k3/8/8/2p5/4P3/3P4/8/4K3 w - - 0 1");
+
+        assert_eq!(squares(entry.passed[Color::White.to_index()]), [Square::E4]);
+        assert_eq!(squares(entry.passed[Color::Black.to_index()]), []);
+
+        let (_, entry) = pawn_entry("4k3/p7/8/8/8/8/1P6/4K3 w - - 0 1");
+
+        assert_eq!(squares(entry.passed[Color::White.to_index()]), []);
+        assert_eq!(squares(entry.passed[Color::Black.to_index()]), []);
+    }
+
+    #[test]
+    fn passed_pawn_bonus_grows_with_rank_and_free_path()
+    {
+        let params = EvalParams::default();
+        let (board, _) = pawn_entry("k7/8/4P3/8/8/8/8/2K5 w - - 0 1");
+
+        assert_eq!(passed_pawn_bonus(&board, Square::E6, Color::White, &params), (params.passed_pawn + params.passed_pawn_free_path) * 25.0);
+
+        let (board, _) = pawn_entry("4k3/8/8/8/8/4p3/8/4K3 b - - 0 1");
+
+        assert_eq!(passed_pawn_bonus(&board, Square::E3, Color::Black, &params), params.passed_pawn * 25.0);
+    }
+
+    #[test]
+    fn pawn_key_ignores_other_pieces()
+    {
+        let (_, with_pieces) = pawn_entry("r3k3/pp6/8/8/8/8/PP6/R3K3 w Qq - 0 1");
+        let (_, without) = pawn_entry("4k3/pp6/8/8/8/8/PP6/4K3 w - - 0 1");
+        let (_, moved) = pawn_entry("4k3/pp6/8/8/8/P7/1P6/4K3 w - - 0 1");
+
+        assert_eq!(with_pieces.key, without.key);
+        assert_ne!(with_pieces.key, moved.key);
+    }
+}
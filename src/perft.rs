@@ -0,0 +1,48 @@
+use std::time::Instant;
+use chess::{Board, ChessMove, MoveGen};
+use crate::move_string_conversion::move_to_string;
+
+/// Counts the leaf nodes of the legal move tree of `board` to `depth` plies.
+pub fn perft(board: &Board, depth: u8) -> u64
+{
+    let moves = MoveGen::new_legal(board);
+
+    // The last ply only needs counting, not playing
+    if depth <= 1
+    {
+        return if depth == 0 { 1 } else { moves.len() as u64 };
+    }
+
+    moves
+        .map(|mov| perft(&board.make_move_new(mov), depth - 1))
+        .sum()
+}
+
+/// Perft split by root move, to find the move under which two move
+/// generators disagree.
+pub fn divide(board: &Board, depth: u8) -> Vec<(ChessMove, u64)>
+{
+    MoveGen::new_legal(board)
+        .map(|mov| (mov, perft(&board.make_move_new(mov), depth.saturating_sub(1))))
+        .collect()
+}
+
+/// Prints `divide` in the usual `<move>: <nodes>` format followed by the
+/// total and the time it took.
+pub fn print_divide(board: &Board, depth: u8)
+{
+    let start = Instant::now();
+    let counts = divide(board, depth);
+    let elapsed = start.elapsed().as_millis() as u64;
+
+    for (mov, nodes) in &counts
+    {
+        println!("{}: {}", move_to_string(*mov), nodes);
+    }
+
+    let total = counts.iter().map(|(_, nodes)| nodes).sum::<u64>();
+
+    println!();
+    println!("Nodes searched: {}", total);
+    println!("Time: {} ms ({} nps)", elapsed, total * 1000 / elapsed.max(1));
+}
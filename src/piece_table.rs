@@ -1,7 +1,8 @@
-use chess::{Board, Color, Piece};
-use crate::material::material;
+use chess::{Color, Piece, Square};
+use crate::eval_params::EvalParams;
+use crate::score::Score;
 
-pub const KING_SQUARE_TABLE: [f32; 64] = 
+const KING_TABLE_MG: [f32; 64] = 
 [
     -0.65, 0.23, 0.16, -0.10, -0.10, -0.10, 0.02, 0.13,
     -0.24, -0.15, -0.20, -0.20, -0.20, -0.20, -0.38, -0.29,
@@ -13,7 +14,7 @@ pub const KING_SQUARE_TABLE: [f32; 64] =
     -0.15, 0.36, 0.12, -0.54, 0.08, -0.28, 0.24, 0.14
 ];
 
-pub const KING_SQUARE_TABLE_ENDGAME: [f32; 64] = 
+const KING_TABLE_EG: [f32; 64] = 
 [
     -0.74, -0.35, -0.18, -0.18, -0.11,  0.15,  0.04, -0.17,
     -0.12,  0.17,  0.14,  0.17,  0.17,  0.38,  0.23,  0.11,
@@ -25,7 +26,7 @@ pub const KING_SQUARE_TABLE_ENDGAME: [f32; 64] =
     -0.53, -0.34, -0.21, -0.11, -0.28, -0.14, -0.24, -0.43
 ];
 
-pub const PAWN_SQUARE_TABLE: [f32; 64] = 
+const PAWN_TABLE_MG: [f32; 64] = 
 [
     0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
     1.98, 1.34, 1.61, 1.95, 1.68, 1.26, 1.34, 1.38,
@@ -37,7 +38,7 @@ pub const PAWN_SQUARE_TABLE: [f32; 64] =
     0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0
 ];
 
-pub const PAWN_SQUARE_TABLE_ENDGAME: [f32; 64] = 
+const PAWN_TABLE_EG: [f32; 64] = 
 [
     0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
     6.5, 6.0, 6.0, 6.0, 6.0, 6.0, 6.0, 6.5,
@@ -49,51 +50,122 @@ pub const PAWN_SQUARE_TABLE_ENDGAME: [f32; 64] =
     0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0,
 ];
 
-fn flip_index(index: usize) -> usize 
-{
-    63 - index
-}
+const KNIGHT_TABLE_MG: [f32; 64] = 
+[
+    -0.50, -0.40, -0.30, -0.30, -0.30, -0.30, -0.40, -0.50,
+    -0.40, -0.20,  0.00,  0.00,  0.00,  0.00, -0.20, -0.40,
+    -0.30,  0.00,  0.10,  0.15,  0.15,  0.10,  0.00, -0.30,
+    -0.30,  0.05,  0.15,  0.20,  0.20,  0.15,  0.05, -0.30,
+    -0.30,  0.00,  0.15,  0.20,  0.20,  0.15,  0.00, -0.30,
+    -0.30,  0.05,  0.10,  0.15,  0.15,  0.10,  0.05, -0.30,
+    -0.40, -0.20,  0.00,  0.05,  0.05,  0.00, -0.20, -0.40,
+    -0.50, -0.40, -0.30, -0.30, -0.30, -0.30, -0.40, -0.50
+];
 
-pub fn no_castle_rights(board: &Board, color: Color) -> bool
-{
-    let rights = board.castle_rights(color);
-    !(rights.has_kingside() || rights.has_queenside())
-}
+const KNIGHT_TABLE_EG: [f32; 64] = 
+[
+    -0.50, -0.40, -0.30, -0.25, -0.25, -0.30, -0.40, -0.50,
+    -0.40, -0.25, -0.10, -0.05, -0.05, -0.10, -0.25, -0.40,
+    -0.30, -0.10,  0.05,  0.10,  0.10,  0.05, -0.10, -0.30,
+    -0.25, -0.05,  0.10,  0.15,  0.15,  0.10, -0.05, -0.25,
+    -0.25, -0.05,  0.10,  0.15,  0.15,  0.10, -0.05, -0.25,
+    -0.30, -0.10,  0.05,  0.10,  0.10,  0.05, -0.10, -0.30,
+    -0.40, -0.25, -0.10, -0.05, -0.05, -0.10, -0.25, -0.40,
+    -0.50, -0.40, -0.30, -0.25, -0.25, -0.30, -0.40, -0.50
+];
 
-pub fn pawn_square_value(rank: u8, file: u8, color: Color, is_endgame: bool, board: &Board) -> f32 
-{
-    let idx = (rank * 8 + file) as usize;
-    let sq_table = if is_endgame || no_castle_rights(board, color)
-    {
-        PAWN_SQUARE_TABLE_ENDGAME
-    }
-    else 
-    {
-        PAWN_SQUARE_TABLE
-    };
+const BISHOP_TABLE_MG: [f32; 64] = 
+[
+    -0.20, -0.10, -0.10, -0.10, -0.10, -0.10, -0.10, -0.20,
+    -0.10,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.10,
+    -0.10,  0.00,  0.05,  0.10,  0.10,  0.05,  0.00, -0.10,
+    -0.10,  0.05,  0.05,  0.10,  0.10,  0.05,  0.05, -0.10,
+    -0.10,  0.00,  0.10,  0.10,  0.10,  0.10,  0.00, -0.10,
+    -0.10,  0.10,  0.10,  0.10,  0.10,  0.10,  0.10, -0.10,
+    -0.10,  0.05,  0.00,  0.00,  0.00,  0.00,  0.05, -0.10,
+    -0.20, -0.10, -0.10, -0.10, -0.10, -0.10, -0.10, -0.20
+];
 
-    match color 
-    {
-        Color::White => sq_table[flip_index(idx)] + material(Some(Piece::Pawn)),
-        Color::Black => sq_table[idx] + material(Some(Piece::Pawn)),
-    }
+const BISHOP_TABLE_EG: [f32; 64] = 
+[
+    -0.15, -0.10, -0.08, -0.05, -0.05, -0.08, -0.10, -0.15,
+    -0.10, -0.05,  0.00,  0.02,  0.02,  0.00, -0.05, -0.10,
+    -0.08,  0.00,  0.05,  0.06,  0.06,  0.05,  0.00, -0.08,
+    -0.05,  0.02,  0.06,  0.10,  0.10,  0.06,  0.02, -0.05,
+    -0.05,  0.02,  0.06,  0.10,  0.10,  0.06,  0.02, -0.05,
+    -0.08,  0.00,  0.05,  0.06,  0.06,  0.05,  0.00, -0.08,
+    -0.10, -0.05,  0.00,  0.02,  0.02,  0.00, -0.05, -0.10,
+    -0.15, -0.10, -0.08, -0.05, -0.05, -0.08, -0.10, -0.15
+];
+
+const ROOK_TABLE_MG: [f32; 64] = 
+[
+     0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,
+     0.05,  0.10,  0.10,  0.10,  0.10,  0.10,  0.10,  0.05,
+    -0.05,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.05,
+    -0.05,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.05,
+    -0.05,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.05,
+    -0.05,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.05,
+    -0.05,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.05,
+     0.00,  0.00,  0.00,  0.05,  0.05,  0.00,  0.00,  0.00
+];
+
+const ROOK_TABLE_EG: [f32; 64] = 
+[
+     0.05,  0.05,  0.05,  0.05,  0.05,  0.05,  0.05,  0.05,
+     0.10,  0.10,  0.10,  0.10,  0.10,  0.10,  0.10,  0.10,
+     0.02,  0.02,  0.02,  0.02,  0.02,  0.02,  0.02,  0.02,
+     0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,
+     0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,
+     0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00,
+    -0.02, -0.02, -0.02, -0.02, -0.02, -0.02, -0.02, -0.02,
+    -0.03, -0.03, -0.03, -0.03, -0.03, -0.03, -0.03, -0.03
+];
+
+const QUEEN_TABLE_MG: [f32; 64] = 
+[
+    -0.20, -0.10, -0.10, -0.05, -0.05, -0.10, -0.10, -0.20,
+    -0.10,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.10,
+    -0.10,  0.00,  0.05,  0.05,  0.05,  0.05,  0.00, -0.10,
+    -0.05,  0.00,  0.05,  0.05,  0.05,  0.05,  0.00, -0.05,
+    -0.05,  0.00,  0.05,  0.05,  0.05,  0.05,  0.00, -0.05,
+    -0.10,  0.00,  0.05,  0.05,  0.05,  0.05,  0.00, -0.10,
+    -0.10,  0.00,  0.00,  0.00,  0.00,  0.00,  0.00, -0.10,
+    -0.20, -0.10, -0.10, -0.05, -0.05, -0.10, -0.10, -0.20
+];
+
+const QUEEN_TABLE_EG: [f32; 64] = 
+[
+    -0.20, -0.10, -0.10, -0.05, -0.05, -0.10, -0.10, -0.20,
+    -0.10,  0.00,  0.05,  0.05,  0.05,  0.05,  0.00, -0.10,
+    -0.10,  0.05,  0.10,  0.10,  0.10,  0.10,  0.05, -0.10,
+    -0.05,  0.05,  0.10,  0.15,  0.15,  0.10,  0.05, -0.05,
+    -0.05,  0.05,  0.10,  0.15,  0.15,  0.10,  0.05, -0.05,
+    -0.10,  0.05,  0.10,  0.10,  0.10,  0.10,  0.05, -0.10,
+    -0.10,  0.00,  0.05,  0.05,  0.05,  0.05,  0.00, -0.10,
+    -0.20, -0.10, -0.10, -0.05, -0.05, -0.10, -0.10, -0.20
+];
+
+/// Middlegame and endgame tables of every piece type, in `Piece::to_index`
+/// order.
+pub const MG_TABLES: [[f32; 64]; 6] = [PAWN_TABLE_MG, KNIGHT_TABLE_MG, BISHOP_TABLE_MG, ROOK_TABLE_MG, QUEEN_TABLE_MG, KING_TABLE_MG];
+pub const EG_TABLES: [[f32; 64]; 6] = [PAWN_TABLE_EG, KNIGHT_TABLE_EG, BISHOP_TABLE_EG, ROOK_TABLE_EG, QUEEN_TABLE_EG, KING_TABLE_EG];
+
+/// The tables are written from White's side with the eighth rank first, so
+/// White's squares are flipped vertically and Black's are read as they are.
+fn flip_index(index: usize) -> usize 
+{
+    index ^ 56
 }
 
-pub fn king_square_value(rank: u8, file: u8, color: Color, is_endgame: bool, board: &Board) -> f32 
+pub fn piece_square_value(piece: Piece, square: Square, color: Color, params: &EvalParams) -> Score 
 {
-    let idx = (rank * 8 + file) as usize;
-    let sq_table = if is_endgame || no_castle_rights(board, board.side_to_move())
-    {
-        KING_SQUARE_TABLE_ENDGAME
-    }
-    else 
-    {
-        KING_SQUARE_TABLE
-    };
+    let idx = square.to_index();
+    let sq_table = &params.piece_tables[piece.to_index()];
 
     match color 
     {
-        Color::White => sq_table[flip_index(idx)] + material(Some(Piece::King)),
-        Color::Black => sq_table[idx] + material(Some(Piece::King)),
+        Color::White => sq_table[flip_index(idx)],
+        Color::Black => sq_table[idx],
     }
 }
@@ -0,0 +1,264 @@
+use std::fs;
+use std::io;
+use chess::{BitBoard, Board, CastleRights, ChessMove, Color, File, Piece, Rank, Square, ALL_SQUARES, EMPTY};
+use rand::Rng;
+use shakmaty::zobrist::{ZobristValue, Zobrist64};
+use shakmaty::CastlingSide;
+use crate::shakmaty_conversion::{shakmaty_color, shakmaty_piece, shakmaty_square};
+
+const ENTRY_SIZE: usize = 16;
+
+struct PolyglotEntry
+{
+    key: u64,
+    mov: u16,
+    weight: u16
+}
+
+/// A standard Polyglot `.bin` opening book, entries sorted by key.
+pub struct PolyglotBook
+{
+    entries: Vec<PolyglotEntry>
+}
+
+/// The Polyglot Zobrist key of `board`. Shakmaty's 64 bit Zobrist values are
+/// the Polyglot random numbers, so only the hashing rules live here.
+pub fn polyglot_key(board: &Board) -> u64
+{
+    let mut key = Zobrist64::default();
+
+    for square in ALL_SQUARES
+    {
+        if let (Some(piece), Some(color)) = (board.piece_on(square), board.color_on(square))
+        {
+            key ^= Zobrist64::zobrist_for_piece(shakmaty_square(square), shakmaty_piece(piece, color));
+        }
+    }
+
+    for color in [Color::White, Color::Black]
+    {
+        let rights = board.castle_rights(color);
+
+        if rights.has_kingside()
+        {
+            key ^= Zobrist64::zobrist_for_castling_right(shakmaty_color(color), CastlingSide::KingSide);
+        }
+
+        if rights.has_queenside()
+        {
+            key ^= Zobrist64::zobrist_for_castling_right(shakmaty_color(color), CastlingSide::QueenSide);
+        }
+    }
+
+    // The en passant file only counts when a pawn of the side to move stands
+    // next to the pawn that just made its double step
+    if let Some(square) = board.en_passant()
+    {
+        let pawns = board.pieces(Piece::Pawn) & board.color_combined(board.side_to_move());
+        let neighbours = [square.left(), square.right()];
+
+        if neighbours.iter().flatten().any(|neighbour| pawns & BitBoard::from_square(*neighbour) != EMPTY)
+        {
+            key ^= Zobrist64::zobrist_for_en_passant_file(shakmaty_square(square).file());
+        }
+    }
+
+    if board.side_to_move() == Color::White
+    {
+        key ^= Zobrist64::zobrist_for_white_turn();
+    }
+
+    key.0
+}
+
+/// Polyglot writes castling as the king taking its own rook.
+fn decode_move(board: &Board, mov: u16) -> Option<ChessMove>
+{
+    let square = |file: u16, rank: u16| Square::make_square(Rank::from_index(rank as usize), File::from_index(file as usize));
+
+    let source = square((mov >> 6) & 0x7, (mov >> 9) & 0x7);
+    let mut dest = square(mov & 0x7, (mov >> 3) & 0x7);
+
+    let promotion = match (mov >> 12) & 0x7
+    {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None
+    };
+
+    if board.piece_on(source) == Some(Piece::King) && board.castle_rights(board.side_to_move()) != CastleRights::NoRights
+    {
+        dest = match (source, dest)
+        {
+            (Square::E1, Square::H1) => Square::G1,
+            (Square::E1, Square::A1) => Square::C1,
+            (Square::E8, Square::H8) => Square::G8,
+            (Square::E8, Square::A8) => Square::C8,
+            _ => dest
+        };
+    }
+
+    let chess_move = ChessMove::new(source, dest, promotion);
+
+    if board.legal(chess_move) { Some(chess_move) } else { None }
+}
+
+impl PolyglotBook
+{
+    pub fn load(path: &str) -> io::Result<PolyglotBook>
+    {
+        let bytes = fs::read(path)?;
+
+        let entries = bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|chunk| PolyglotEntry
+            {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                mov: u16::from_be_bytes(chunk[8..10].try_into().unwrap()),
+                weight: u16::from_be_bytes(chunk[10..12].try_into().unwrap())
+            })
+            .collect();
+
+        Ok(PolyglotBook { entries })
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.entries.len()
+    }
+
+    /// Picks among the book moves of `board` either the one with the highest
+    /// weight or a random one in proportion to the weights.
+    pub fn get_move(&self, board: &Board, best_move: bool) -> Option<ChessMove>
+    {
+        let key = polyglot_key(board);
+        let first = self.entries.partition_point(|entry| entry.key < key);
+
+        let candidates = self.entries[first..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| decode_move(board, entry.mov).map(|mov| (mov, entry.weight as u32)))
+            .collect::<Vec<(ChessMove, u32)>>();
+
+        if best_move
+        {
+            return candidates.iter().max_by_key(|(_, weight)| *weight).map(|(mov, _)| *mov);
+        }
+
+        let total = candidates.iter().map(|(_, weight)| weight).sum::<u32>();
+
+        if total == 0
+        {
+            return candidates.first().map(|(mov, _)| *mov);
+        }
+
+        let mut pick = rand::thread_rng().gen_range(0..total);
+
+        for (mov, weight) in &candidates
+        {
+            if pick < *weight
+            {
+                return Some(*mov);
+            }
+            pick -= weight;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    fn encode(source: Square, dest: Square) -> u16
+    {
+        let square = |square: Square| (square.get_rank().to_index() << 3 | square.get_file().to_index()) as u16;
+        square(source) << 6 | square(dest)
+    }
+
+    fn key_after(moves: &str) -> u64
+    {
+        let board = moves
+            .split_whitespace()
+            .fold(Board::default(), |board, mov| board.make_move_new(ChessMove::from_str(mov).unwrap()));
+
+        polyglot_key(&board)
+    }
+
+    /// The test positions of the Polyglot book format specification.
+    #[test]
+    fn published_keys()
+    {
+        let keys =
+        [
+            ("", 0x463b96181691fc9c),
+            ("e2e4", 0x823c9b50fd114196),
+            ("e2e4 d7d5", 0x0756b94461c50fb0),
+            ("e2e4 d7d5 e4e5", 0x662fafb965db29d4),
+            ("e2e4 d7d5 e4e5 f7f5", 0x22a48b5a8e47ff78),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2", 0x652a607ca3f242c1),
+            ("e2e4 d7d5 e4e5 f7f5 e1e2 e8f7", 0x00fdd303c946bdd9),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4", 0x3c8123ea7b067637),
+            ("a2a4 b7b5 h2h4 b5b4 c2c4 b4c3 a1a3", 0x5c3f9b829b279560)
+        ];
+
+        for (moves, key) in keys
+        {
+            assert_eq!(key_after(moves), key, "after {:?}", moves);
+        }
+    }
+
+    #[test]
+    fn castling_is_decoded_from_king_takes_rook()
+    {
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+
+        assert_eq!(decode_move(&board, encode(Square::E1, Square::H1)), Some(ChessMove::new(Square::E1, Square::G1, None)));
+        assert_eq!(decode_move(&board, encode(Square::E1, Square::A1)), Some(ChessMove::new(Square::E1, Square::C1, None)));
+
+        let board = Board::from_str("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+
+        assert_eq!(decode_move(&board, encode(Square::E8, Square::H8)), Some(ChessMove::new(Square::E8, Square::G8, None)));
+        assert_eq!(decode_move(&board, encode(Square::E8, Square::A8)), Some(ChessMove::new(Square::E8, Square::C8, None)));
+    }
+
+    #[test]
+    fn promotions_and_illegal_moves_are_decoded()
+    {
+        let board = Board::from_str("8/P6k/8/8/8/8/8/K7 w - - 0 1").unwrap();
+        let knight = 1 << 12 | encode(Square::A7, Square::A8);
+
+        assert_eq!(decode_move(&board, knight), Some(ChessMove::new(Square::A7, Square::A8, Some(Piece::Knight))));
+        assert_eq!(decode_move(&board, encode(Square::A1, Square::A3)), None);
+    }
+
+    #[test]
+    fn book_moves_are_looked_up_by_key()
+    {
+        let path = std::env::temp_dir().join(format!("sigma0-book-{}.bin", std::process::id()));
+        let key = polyglot_key(&Board::default());
+        let mut bytes = Vec::new();
+
+        for (mov, weight) in [(encode(Square::D2, Square::D4), 1u16), (encode(Square::E2, Square::E4), 10)]
+        {
+            bytes.extend(key.to_be_bytes());
+            bytes.extend(mov.to_be_bytes());
+            bytes.extend(weight.to_be_bytes());
+            bytes.extend(0u32.to_be_bytes());
+        }
+
+        fs::write(&path, bytes).unwrap();
+        let book = PolyglotBook::load(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(book.len(), 2);
+        assert_eq!(book.get_move(&Board::default(), true), Some(ChessMove::new(Square::E2, Square::E4, None)));
+        assert!(book.get_move(&Board::default(), false).is_some());
+        assert_eq!(book.get_move(&Board::default().make_move_new(ChessMove::new(Square::E2, Square::E4, None)), true), None);
+    }
+}
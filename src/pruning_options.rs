@@ -0,0 +1,28 @@
+/// Which selective search techniques `negamax` applies, each switched by a
+/// hidden UCI option so it can be tested on its own.
+#[derive(Clone, Copy)]
+pub struct PruningOptions
+{
+    pub null_move: bool,
+    pub late_move_reductions: bool,
+    pub reverse_futility: bool,
+    pub futility: bool,
+    pub razoring: bool,
+    pub late_move_pruning: bool
+}
+
+impl Default for PruningOptions
+{
+    fn default() -> Self
+    {
+        PruningOptions
+        {
+            null_move: true,
+            late_move_reductions: true,
+            reverse_futility: true,
+            futility: true,
+            razoring: true,
+            late_move_pruning: true
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use std::env;
+use std::str::FromStr;
+use chess::{Board, ChessMove};
+use redis::{Client, Commands, Connection};
+use crate::move_string_conversion::move_to_string;
+use crate::opening_book::OpeningBook;
+
+/// Book moves stored as FEN to UCI move pairs on the server named by
+/// `REDIS_CONNECTION`.
+pub struct RedisBook
+{
+    conn: Connection
+}
+
+impl RedisBook
+{
+    pub fn connect() -> Option<RedisBook>
+    {
+        let conn = env::var("REDIS_CONNECTION")
+            .ok()
+            .and_then(|connection| Client::open(connection).ok())
+            .and_then(|client| client.get_connection().ok())?;
+
+        Some(RedisBook { conn })
+    }
+}
+
+impl OpeningBook for RedisBook
+{
+    fn get_move(&mut self, board: &Board) -> Option<ChessMove>
+    {
+        let uci = self.conn.get(board.to_string())
+            .map_or(None, |uci: String| Some(uci));
+
+        uci.and_then(|uci| ChessMove::from_str(&uci).ok())
+    }
+
+    fn add_move(&mut self, board: &Board, mov: ChessMove)
+    {
+        let _ = self.conn.set::<String, String, String>(board.to_string(), move_to_string(mov));
+    }
+}
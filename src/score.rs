@@ -0,0 +1,92 @@
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub};
+use crate::consts::TOTAL_PHASE;
+
+/// An evaluation term's value in the middlegame and in the endgame, blended
+/// by the game phase once the whole evaluation is added up.
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct Score
+{
+    pub mg: f32,
+    pub eg: f32
+}
+
+impl Score
+{
+    pub const ZERO: Score = Score::new(0.0, 0.0);
+
+    pub const fn new(mg: f32, eg: f32) -> Score
+    {
+        Score { mg, eg }
+    }
+
+    /// The same value in both phases.
+    pub const fn splat(value: f32) -> Score
+    {
+        Score::new(value, value)
+    }
+
+    /// `phase` runs from 0 with only kings and pawns left up to
+    /// `TOTAL_PHASE` with all the pieces on the board.
+    pub fn taper(self, phase: i32) -> f32
+    {
+        (self.mg * phase as f32 + self.eg * (TOTAL_PHASE - phase) as f32) / TOTAL_PHASE as f32
+    }
+}
+
+impl Add for Score
+{
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score
+    {
+        Score::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl AddAssign for Score
+{
+    fn add_assign(&mut self, other: Score)
+    {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Score
+{
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score
+    {
+        Score::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl Neg for Score
+{
+    type Output = Score;
+
+    fn neg(self) -> Score
+    {
+        Score::new(-self.mg, -self.eg)
+    }
+}
+
+impl Mul<f32> for Score
+{
+    type Output = Score;
+
+    fn mul(self, factor: f32) -> Score
+    {
+        Score::new(self.mg * factor, self.eg * factor)
+    }
+}
+
+impl Div<f32> for Score
+{
+    type Output = Score;
+
+    fn div(self, divisor: f32) -> Score
+    {
+        Score::new(self.mg / divisor, self.eg / divisor)
+    }
+}
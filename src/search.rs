@@ -1,72 +1,131 @@
 
-use std::sync::MutexGuard;
+use std::sync::Arc;
+use std::thread;
 
-use chess::{Board, ChessMove, Color, MoveGen, EMPTY};
-use crate::consts::{ENDGAME_PIECE_FOR_GREATER_DEPTH, ENDGAME_SEARCH_DEPTH, MAX_PIECE_FOR_ENDGAME, OPENING_FOR_DIFF_EVAL, OPENING_SEARCH_DEPTH, SEARCH_DEPTH, USE_QUIESCENSE_SEARCH_AFTER_NPLIES};
+use chess::{Board, BoardStatus, Color, MoveGen, Piece, EMPTY};
+use shakmaty_syzygy::Wdl;
+use crate::consts::{ASPIRATION_MAX_WINDOW, ASPIRATION_MIN_DEPTH, ASPIRATION_WINDOW, ENDGAME_PIECE_FOR_GREATER_DEPTH, ENDGAME_SEARCH_DEPTH, FUTILITY_MARGIN, LATE_MOVE_PRUNING_DEPTH, LATE_MOVE_PRUNING_MOVES, LMR_MIN_DEPTH, LMR_MIN_MOVES, MATE_SCORE, MATE_THRESHOLD, MAX_PIECE_FOR_ENDGAME, MAX_SEARCH_DEPTH, NULL_MOVE_MIN_DEPTH, NULL_MOVE_REDUCTION, NULL_MOVE_VERIFICATION_PIECES, NULL_WINDOW, OPENING_FOR_DIFF_EVAL, OPENING_SEARCH_DEPTH, QUIESCENCE_DEPTH, RAZORING_DEPTH, RAZORING_MARGIN, REVERSE_FUTILITY_DEPTH, REVERSE_FUTILITY_MARGIN, SEARCH_DEPTH, TABLEBASE_WIN_SCORE, USE_QUIESCENSE_SEARCH_AFTER_NPLIES};
 use crate::endgame_tablebase::EndGameTablebase;
-use crate::eval::{count_all_pieces, eval, is_bad_king_move, is_terminal};
-use crate::move_database::MoveDatabase;
+use crate::eval::{count_all_pieces, is_terminal, white_score};
+use crate::extension_policy::move_swing;
+use crate::game_history::GameHistory;
+use crate::move_ordering::{good_captures, is_capture};
+use crate::move_string_conversion::move_to_string;
+use crate::opening_book::OpeningBook;
+use crate::search_context::SearchContext;
+use crate::search_limits::SearchLimits;
+use crate::search_signals::SearchSignals;
 use crate::search_move::SearchMove;
-use crate::transposition_table::TranspostionTable;
+use crate::search_options::SearchOptions;
+use crate::transposition_table::{Bound, TranspostionTable};
+
+pub fn is_mate_score(score: f32) -> bool
+{
+    score.abs() >= MATE_THRESHOLD
+}
+
+/// `eval` scores every checkmate as infinite, the search needs the distance
+/// from the root to prefer the fastest mate and to report `score mate N`.
+/// The score is relative to the side to move.
+fn leaf_eval(board: &Board, plies: i32, ctx: &mut SearchContext) -> f32
+{
+    let score = white_score(ctx.evaluator.evaluate(board, plies), board.side_to_move());
+
+    if score.is_infinite()
+    {
+        score.signum() * (MATE_SCORE - ctx.ply(plies) as f32)
+    }
+    else
+    {
+        score
+    }
+}
+
+/// Tablebase wins rank below any mate found by the search, cursed wins and
+/// blessed losses are draws under the fifty-move rule.
+fn tablebase_score(wdl: Wdl, ply: u32) -> f32
+{
+    match wdl
+    {
+        Wdl::Win => TABLEBASE_WIN_SCORE - ply as f32,
+        Wdl::Loss => -(TABLEBASE_WIN_SCORE - ply as f32),
+        Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0.0
+    }
+}
 
 fn quiescence_search(
     board: &Board,
     mut alpha: f32,
-    mut beta: f32,
-    maximizing_player: bool,
+    beta: f32,
     depth: u8,
     plies: i32,
-    transposition_table: &mut TranspostionTable
+    ctx: &mut SearchContext
 ) -> SearchMove {
+    if ctx.enter_node(plies)
+    {
+        return SearchMove::new(None, 0.0);
+    }
+
     if depth == 0 || is_terminal(board.status()) 
     {
-        return SearchMove::new(None, eval(board, MoveGen::new_legal(board).collect(), plies, false));
+        return SearchMove::new(None, leaf_eval(board, plies, ctx));
     }
 
-    let _eval = eval(board, MoveGen::new_legal(board).collect(), plies, false);
-    let mut best_move = SearchMove::new(None, _eval);
+    let in_check = board.checkers() != &EMPTY;
+
+    // In check every evasion is searched, standing pat isn't an option
+    let mut best_move = if in_check
+    {
+        SearchMove::new(None, f32::NEG_INFINITY)
+    }
+    else
+    {
+        let stand_pat = white_score(ctx.evaluator.evaluate(board, plies), board.side_to_move());
+        SearchMove::new(None, stand_pat)
+    };
 
-    let moves_ordered = order_moves_by_evaluation(board, MoveGen::new_legal(board).collect(), maximizing_player, plies);
+    if best_move.eval() >= beta
+    {
+        return best_move;
+    }
 
-    for mv in moves_ordered {
-        if board.piece_on(mv.get_dest()).is_none() || board.checkers() == &EMPTY 
-        {
-            continue;
-        }
+    alpha = alpha.max(best_move.eval());
 
+    // Out of check, captures losing material can't improve on standing pat
+    let moves_ordered = if in_check
+    {
+        ctx.move_ordering.order(board, MoveGen::new_legal(board).collect(), None, ctx.ply(plies), None)
+    }
+    else
+    {
+        good_captures(board)
+    };
+
+    for mv in moves_ordered
+    {
         let next_board = board.make_move_new(mv);
-        let eval_mv;
+        ctx.evaluator.make_move(board, mv, &next_board);
+
+        let child = quiescence_search(&next_board, -beta, -alpha, depth - 1, plies + 1, ctx);
 
-        if let Some(val) = transposition_table.get_position(&next_board) 
+        ctx.evaluator.unmake_move();
+
+        if ctx.stopped()
         {
-            eval_mv = val.clone();
-        } else {
-            eval_mv = quiescence_search(
-                &next_board,
-                alpha,
-                beta,
-                !maximizing_player,
-                depth - 1,
-                plies + 1,
-                transposition_table
-            );
-
-            transposition_table.add_position(&next_board, &eval_mv);
+            return best_move;
         }
 
-        if maximizing_player {
-            if eval_mv.eval() > best_move.eval() {
-                best_move = SearchMove::new(Some(mv), eval_mv.eval());
-            }
-            alpha = alpha.max(best_move.eval());
-        } else {
-            if eval_mv.eval() < best_move.eval() {
-                best_move = SearchMove::new(Some(mv), eval_mv.eval());
-            }
-            beta = beta.min(best_move.eval());
+        let evaluation = -child.eval();
+
+        if evaluation > best_move.eval()
+        {
+            best_move = SearchMove::with_pv(mv, evaluation, &child);
         }
 
-        if beta <= alpha {
+        alpha = alpha.max(evaluation);
+
+        if alpha >= beta
+        {
             break;
         }
     }
@@ -74,227 +133,532 @@ fn quiescence_search(
     best_move
 }
 
-pub fn alpha_beta(
+/// The position at the end of the quiescence search's principal variation,
+/// where the static evaluation can be trusted, and its ply count.
+pub fn quiet_position(board: &Board, plies: i32, ctx: &mut SearchContext) -> (Board, i32)
+{
+    ctx.evaluator.reset(board);
+
+    let search_move = quiescence_search(board, f32::NEG_INFINITY, f32::INFINITY, QUIESCENCE_DEPTH, plies, ctx);
+    let leaf = search_move.pv().iter().fold(*board, |board, mov| board.make_move_new(*mov));
+
+    (leaf, plies + search_move.pv().len() as i32)
+}
+
+/// Pieces other than pawns and the king the side to move has.
+fn non_pawn_pieces(board: &Board) -> u32
+{
+    let pawns_and_king = board.pieces(Piece::Pawn) | board.pieces(Piece::King);
+
+    (board.color_combined(board.side_to_move()) & !pawns_and_king).popcnt()
+}
+
+/// Plies taken off a late quiet move, growing with the depth and with how
+/// far down the ordering the move comes.
+fn late_move_reduction(depth: u8, moves_searched: usize, pv_node: bool) -> u8
+{
+    let reduction = 0.75 + (depth as f32).ln() * (moves_searched as f32).ln() / 2.25;
+
+    (reduction as u8).saturating_sub(pv_node as u8)
+}
+
+/// Principal variation search. Scores are from the side to move's point of
+/// view, the first move gets the full window and the others a null window,
+/// searched again with the full one when they beat `alpha`.
+#[allow(clippy::too_many_arguments)]
+fn negamax(
     board: &Board,
     depth: u8,
     mut alpha: f32,
-    mut beta: f32,
-    maximizing_player: bool,
+    beta: f32,
     plies: i32,
     extended: bool,
     de_extended: bool,
-    transposition_table: &mut TranspostionTable
+    ctx: &mut SearchContext
 ) -> SearchMove 
 {
-    let board_eval = eval(board, MoveGen::new_legal(board).collect(), plies, false);
+    if ctx.enter_node(plies)
+    {
+        return SearchMove::new(None, 0.0);
+    }
+
+    let ply = ctx.ply(plies);
+
+    // A fifty-move draw is only claimed if the last move didn't mate
+    let is_draw = ply > 0 && (ctx.history.is_repetition(ctx.root_plies())
+        || (ctx.history.is_fifty_move_draw() && board.status() != BoardStatus::Checkmate));
+
+    if is_draw
+    {
+        return SearchMove::new(None, white_score(ctx.draw_score(), board.side_to_move()));
+    }
+
+    let tt_entry = ctx.transposition_table.get_position(board);
+
+    // The root always searches, so that there is a move to play
+    if let Some(entry) = tt_entry.filter(|entry| ply > 0 && entry.depth() >= depth)
+    {
+        let score = entry.eval(ply);
+
+        let usable = match entry.bound()
+        {
+            Bound::Exact => true,
+            Bound::Lower => score >= beta,
+            Bound::Upper => score <= alpha
+        };
+
+        if usable
+        {
+            return SearchMove::new(entry.mov(), score);
+        }
+    }
+
+    if let Some(wdl) = ctx.syzygy().filter(|_| ply > 0).and_then(|tablebase| tablebase.probe_wdl(board, ctx.history.halfmove_clock()))
+    {
+        let score = tablebase_score(wdl, ply);
+
+        ctx.transposition_table.add_position(board, MAX_SEARCH_DEPTH, Bound::Exact, None, score, ply);
+        return SearchMove::new(None, score);
+    }
 
     if depth == 0 || is_terminal(board.status()) 
     {
         let mov = if plies > USE_QUIESCENSE_SEARCH_AFTER_NPLIES 
         {
-            quiescence_search(board, alpha, beta, maximizing_player, 4, plies, transposition_table)
+            quiescence_search(board, alpha, beta, QUIESCENCE_DEPTH, plies, ctx)
         } 
         else 
         {
-            SearchMove::new(None, eval(board, MoveGen::new_legal(board).collect(), plies, false))
+            SearchMove::new(None, leaf_eval(board, plies, ctx))
         };
         return mov;
     }
 
-    let mut best_move = if maximizing_player 
+    let alpha_orig = alpha;
+    let pruning = ctx.pruning();
+    let policy = ctx.extension_policy();
+    let in_check = board.checkers() != &EMPTY;
+
+    // PVS searches every move but the first of a node with a null window
+    let pv_node = beta - alpha > 2.0 * NULL_WINDOW;
+    let static_eval = white_score(ctx.evaluator.evaluate(board, plies), board.side_to_move());
+    let prune = !pv_node && !in_check && !is_mate_score(beta);
+
+    if prune && pruning.reverse_futility && depth <= REVERSE_FUTILITY_DEPTH
+        && static_eval - REVERSE_FUTILITY_MARGIN * depth as f32 >= beta
     {
-        SearchMove::new(None, f32::NEG_INFINITY)
-    } 
-    else 
+        return SearchMove::new(None, static_eval);
+    }
+
+    if prune && pruning.razoring && depth <= RAZORING_DEPTH && static_eval + RAZORING_MARGIN * (depth as f32) < alpha
     {
-        SearchMove::new(None, f32::INFINITY)
-    };
+        let search_move = quiescence_search(board, alpha, beta, QUIESCENCE_DEPTH, plies, ctx);
 
-    let moves_ordered = order_moves_by_evaluation(board, MoveGen::new_legal(board).collect(), maximizing_player, plies);
-    let pieces = count_all_pieces(&board);
+        if search_move.eval() <= alpha
+        {
+            return SearchMove::new(None, search_move.eval());
+        }
+    }
 
-    for mv in moves_ordered 
+    let non_pawn_pieces = non_pawn_pieces(board);
+
+    // No two null moves in a row, none while verifying one, and never
+    // without pieces to move: pawn endings are where passing is most often
+    // the best move
+    let try_null_move = prune && pruning.null_move && depth >= NULL_MOVE_MIN_DEPTH && static_eval >= beta
+        && non_pawn_pieces > 0 && ctx.history.last_move().is_some() && !ctx.verifying_null_move;
+
+    if let Some(null_board) = board.null_move().filter(|_| try_null_move)
     {
-        if is_bad_king_move(&board, &mv, pieces)
+        let reduced_depth = depth.saturating_sub(1 + NULL_MOVE_REDUCTION + depth / 6);
+
+        ctx.evaluator.make_null_move(&null_board);
+        ctx.history.push_null(&null_board);
+        let child = negamax(&null_board, reduced_depth, -beta, -beta + NULL_WINDOW, plies + 1, extended, de_extended, ctx);
+        ctx.history.pop();
+        ctx.evaluator.unmake_move();
+
+        if ctx.stopped()
         {
-            continue
+            return SearchMove::new(None, 0.0);
         }
 
-        let next_board = board.make_move_new(mv);
-        let mut eval_mv = None;
+        let score = -child.eval();
 
-        if let Some(val) = transposition_table.get_position(&next_board) 
-        {
-            eval_mv = Some(val.clone());
-        } 
-        else 
+        if score >= beta
         {
-            let curr_eval = if let Some(mv) = eval_mv
+            // With few pieces left the side to move may be in zugzwang, so a
+            // reduced search without the null move has to confirm the cutoff
+            let verified = non_pawn_pieces > NULL_MOVE_VERIFICATION_PIECES ||
+            {
+                ctx.verifying_null_move = true;
+                let verification = negamax(board, reduced_depth.max(1), beta - NULL_WINDOW, beta, plies, extended, de_extended, ctx);
+                ctx.verifying_null_move = false;
+
+                verification.eval() >= beta
+            };
+
+            if verified && !ctx.stopped()
             {
-                mv.eval()
+                return SearchMove::new(None, if is_mate_score(score) { beta } else { score });
             }
-            else 
+        }
+    }
+
+    let mut best_move = SearchMove::new(None, f32::NEG_INFINITY);
+
+    let previous_move = ctx.history.last_move();
+    let tt_move = tt_entry.and_then(|entry| entry.mov());
+    let moves_ordered = ctx.move_ordering.order(board, MoveGen::new_legal(board).collect(), tt_move, ply, previous_move);
+    let mut quiets_tried = Vec::new();
+    let mut moves_searched = 0;
+
+    let futile = prune && pruning.futility && depth == 1 && static_eval + FUTILITY_MARGIN <= alpha;
+    let late_move_limit = LATE_MOVE_PRUNING_MOVES + (depth as usize).pow(2);
+
+    for mv in moves_ordered 
+    {
+        let next_board = board.make_move_new(mv);
+        let quiet = !is_capture(board, mv) && mv.get_promotion().is_none() && next_board.checkers() == &EMPTY;
+
+        // Quiet moves are only skipped once a move saved the side from mate
+        if quiet && prune && best_move.eval() > -MATE_THRESHOLD
+        {
+            let late = pruning.late_move_pruning && depth <= LATE_MOVE_PRUNING_DEPTH && moves_searched >= late_move_limit;
+
+            if futile || late
             {
-                eval(&next_board, MoveGen::new_legal(&next_board).collect(), plies, false)
-            };
+                continue;
+            }
+        }
+
+        ctx.evaluator.make_move(board, mv, &next_board);
+
+        let (child_depth, extended, de_extended) = if policy.is_enabled()
+        {
+            policy.child_depth(depth, move_swing(board, mv, &next_board), extended, de_extended)
+        }
+        else
+        {
+            (depth - 1, extended, de_extended)
+        };
 
-            let eval_diff = (board_eval - curr_eval).abs();
-            if eval_diff > 1.5 && !extended
+        let reduction = if pruning.late_move_reductions && quiet && !in_check && depth >= LMR_MIN_DEPTH && moves_searched >= LMR_MIN_MOVES
+        {
+            late_move_reduction(depth, moves_searched, pv_node).min(child_depth.saturating_sub(1))
+        }
+        else
+        {
+            0
+        };
+
+        ctx.history.push(board, mv, &next_board);
+
+        let child = if moves_searched == 0
+        {
+            negamax(&next_board, child_depth, -beta, -alpha, plies + 1, extended, de_extended, ctx)
+        }
+        else
+        {
+            let mut probe = negamax(&next_board, child_depth - reduction, -alpha - NULL_WINDOW, -alpha, plies + 1, extended, de_extended, ctx);
+
+            if reduction > 0 && -probe.eval() > alpha && !ctx.stopped()
             {
-                eval_mv = Some(alpha_beta(
-                    &next_board,
-                    depth,
-                    alpha,
-                    beta,
-                    !maximizing_player,
-                    plies + 1,
-                    true,
-                    de_extended,
-                    transposition_table
-                ));
-            } 
-            else if eval_diff < 1.0 && !de_extended && depth > 1
+                probe = negamax(&next_board, child_depth, -alpha - NULL_WINDOW, -alpha, plies + 1, extended, de_extended, ctx);
+            }
+
+            if -probe.eval() > alpha && -probe.eval() < beta && !ctx.stopped()
             {
-                eval_mv = Some(alpha_beta(
-                    &next_board,
-                    depth - 2,
-                    alpha,
-                    beta,
-                    !maximizing_player,
-                    plies + 1,
-                    extended,
-                    true,
-                    transposition_table
-                ));
-            } 
-            else 
+                negamax(&next_board, child_depth, -beta, -alpha, plies + 1, extended, de_extended, ctx)
+            }
+            else
             {
-                eval_mv = Some(alpha_beta(
-                    &next_board,
-                    depth - 1,
-                    alpha,
-                    beta,
-                    !maximizing_player,
-                    plies + 1,
-                    extended,
-                    de_extended,
-                    transposition_table
-                ));
+                probe
             }
+        };
 
-            let eval_mv = &eval_mv.clone().unwrap();
+        ctx.history.pop();
+        ctx.evaluator.unmake_move();
+        moves_searched += 1;
 
-            transposition_table.add_position(&next_board, eval_mv);
+        if ctx.stopped()
+        {
+            return best_move;
         }
         
-        let evaluation = eval_mv.clone().unwrap().eval();
+        let evaluation = -child.eval();
 
-        if best_move.mov().is_none() 
+        if best_move.mov().is_none() || evaluation > best_move.eval()
         {
-            best_move = SearchMove::new(Some(mv), evaluation);
+            best_move = SearchMove::with_pv(mv, evaluation, &child);
         }
 
-        if maximizing_player 
+        alpha = alpha.max(evaluation);
+
+        if alpha >= beta 
         {
-            if evaluation > best_move.eval() 
+            if !is_capture(board, mv)
             {
-                best_move = SearchMove::new(Some(mv), evaluation);
+                ctx.move_ordering.update(board, mv, depth, ply, previous_move, &quiets_tried);
             }
-            alpha = alpha.max(best_move.eval());
-        } 
-        else 
+            break;
+        }
+
+        if !is_capture(board, mv)
         {
-            if evaluation < best_move.eval() 
+            quiets_tried.push(mv);
+        }
+    }
+
+    let bound = if best_move.eval() <= alpha_orig
+    {
+        Bound::Upper
+    }
+    else if best_move.eval() >= beta
+    {
+        Bound::Lower
+    }
+    else
+    {
+        Bound::Exact
+    };
+
+    ctx.transposition_table.add_position(board, depth, bound, best_move.mov(), best_move.eval(), ply);
+
+    best_move
+}
+
+fn default_search_depth(plies: i32, pieces: u8) -> u8
+{
+    if plies <= OPENING_FOR_DIFF_EVAL 
+    {
+        OPENING_SEARCH_DEPTH
+    } 
+    else if pieces <= ENDGAME_PIECE_FOR_GREATER_DEPTH 
+    {
+        ENDGAME_SEARCH_DEPTH
+    } 
+    else 
+    {
+        SEARCH_DEPTH
+    }
+}
+
+/// Converts a white relative score in pawns to the side to move's point of
+/// view, as `cp <centipawns>` or `mate <moves>`.
+pub fn uci_score(score: f32, side_to_move: Color) -> String
+{
+    let score = if side_to_move == Color::White { score } else { -score };
+
+    if is_mate_score(score)
+    {
+        let plies_to_mate = (MATE_SCORE - score.abs()) as i32;
+        let moves_to_mate = (plies_to_mate + 1) / 2;
+
+        format!("mate {}", if score > 0.0 { moves_to_mate } else { -moves_to_mate })
+    }
+    else
+    {
+        format!("cp {}", (score * 100.0).round() as i32)
+    }
+}
+
+fn print_iteration_info(board: &Board, depth: u8, search_move: &SearchMove, ctx: &SearchContext)
+{
+    let elapsed = ctx.elapsed().as_millis() as u64;
+    let nps = ctx.nodes() * 1000 / elapsed.max(1);
+
+    let pv = search_move.pv()
+        .iter()
+        .map(|mov| move_to_string(*mov))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    println!(
+        "info depth {} seldepth {} score {} nodes {} nps {} time {} hashfull {} pv {}",
+        depth,
+        ctx.seldepth(),
+        uci_score(search_move.eval(), board.side_to_move()),
+        ctx.nodes(),
+        nps,
+        elapsed,
+        ctx.transposition_table.hashfull(),
+        pv
+    );
+}
+
+/// Searches a window around the previous iteration's score, widening the
+/// side that failed until the score falls inside it. Scores are relative to
+/// the side to move.
+fn aspiration_search(board: &Board, depth: u8, plies: i32, previous: Option<f32>, ctx: &mut SearchContext) -> SearchMove
+{
+    let previous = previous.filter(|score| depth >= ASPIRATION_MIN_DEPTH && !is_mate_score(*score));
+
+    let Some(previous) = previous else
+    {
+        return negamax(board, depth, f32::NEG_INFINITY, f32::INFINITY, plies, false, false, ctx);
+    };
+
+    let mut delta = ASPIRATION_WINDOW;
+    let (mut alpha, mut beta) = (previous - delta, previous + delta);
+
+    loop
+    {
+        let search_move = negamax(board, depth, alpha, beta, plies, false, false, ctx);
+        let score = search_move.eval();
+
+        if ctx.stopped() || (score > alpha && score < beta)
+        {
+            return search_move;
+        }
+
+        delta *= 2.0;
+
+        if score <= alpha
+        {
+            alpha = if delta > ASPIRATION_MAX_WINDOW { f32::NEG_INFINITY } else { score - delta };
+        }
+        else
+        {
+            beta = if delta > ASPIRATION_MAX_WINDOW { f32::INFINITY } else { score + delta };
+        }
+    }
+}
+
+fn iterative_deepening(board: &Board, plies: i32, start_depth: u8, max_depth: u8, ctx: &mut SearchContext) -> SearchMove
+{
+    let mut best_move: Option<SearchMove> = None;
+    ctx.evaluator.reset(board);
+
+    for depth in start_depth..=max_depth
+    {
+        let previous = best_move.as_ref().map(|search_move| white_score(search_move.eval(), board.side_to_move()));
+        let search_move = aspiration_search(board, depth, plies, previous, ctx);
+        let score = white_score(search_move.eval(), board.side_to_move());
+        let search_move = search_move.with_eval(score);
+        ctx.flush_nodes();
+
+        // An interrupted iteration is only trusted when nothing better exists yet
+        if ctx.stopped()
+        {
+            if best_move.is_none() && search_move.mov().is_some()
             {
-                best_move = SearchMove::new(Some(mv), evaluation);
+                best_move = Some(search_move);
             }
-            beta = beta.min(best_move.eval());
+            break;
+        }
+
+        if ctx.is_main() && ctx.print_info()
+        {
+            print_iteration_info(board, depth, &search_move, ctx);
         }
 
-        if beta <= alpha 
+        let is_mate = is_mate_score(search_move.eval());
+        best_move = Some(search_move.with_depth(depth));
+
+        if is_mate || !ctx.should_start_iteration()
         {
             break;
         }
     }
 
+    // A search stopped before the first root move came back has no move
     best_move
+        .filter(|search_move| search_move.mov().is_some())
+        .unwrap_or_else(|| SearchMove::new(MoveGen::new_legal(board).next(), 0.0))
 }
 
-fn order_moves_by_evaluation(board: &Board, movegen: Vec<ChessMove>, maximizing_player: bool, plies: i32) -> Vec<ChessMove> 
+/// Searches `board` without consulting books or tablebases at the root.
+pub fn search_position(
+    board: &Board, 
+    history: &GameHistory, 
+    transposition_table: &TranspostionTable,
+    limits: &SearchLimits, 
+    options: &SearchOptions,
+    signals: Arc<SearchSignals>
+) -> SearchMove
 {
-    let mut move_evaluations: Vec<(ChessMove, f32)> = movegen.into_iter().map(|mv: ChessMove| 
-        {
-        let next_board = board.make_move_new(mv);
-        let evaluation = eval(&next_board, MoveGen::new_legal(&next_board).collect(), plies + 1, false);
-        (mv, evaluation)
-    }).collect();
+    let plies = history.plies();
 
-    if maximizing_player 
+    let max_depth = if let Some(depth) = limits.depth
     {
-        move_evaluations.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
-    } 
-    else 
+        depth.clamp(1, MAX_SEARCH_DEPTH)
+    }
+    else if limits.is_empty()
     {
-        move_evaluations.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+        default_search_depth(plies, count_all_pieces(board))
     }
+    else
+    {
+        MAX_SEARCH_DEPTH
+    };
+
+    transposition_table.new_search();
+
+    // Lazy SMP: helpers fill the shared table while the main thread
+    // searches, half of them one ply ahead to desynchronise the threads
+    thread::scope(|scope|
+    {
+        for id in 1..options.threads
+        {
+            let signals = signals.clone();
+
+            scope.spawn(move ||
+            {
+                let mut ctx = SearchContext::helper(transposition_table, board.side_to_move(), history, options, signals);
+                iterative_deepening(board, plies, 1 + (id % 2) as u8, MAX_SEARCH_DEPTH, &mut ctx);
+            });
+        }
+
+        let mut ctx = SearchContext::new(transposition_table, limits, board.side_to_move(), history, options, signals.clone());
+        let start_depth = limits.start_depth.unwrap_or(1).clamp(1, max_depth);
+        let best_move = iterative_deepening(board, plies, start_depth, max_depth, &mut ctx);
 
-    move_evaluations.into_iter().map(|(mv, _)| mv).collect()
+        signals.stop_helpers();
+        best_move
+    })
 }
 
-pub fn engine(board: &Board, plies: i32, mut db: MutexGuard<MoveDatabase>) -> ChessMove 
+pub fn engine(
+    board: &Board, 
+    history: &GameHistory, 
+    book: &mut dyn OpeningBook,
+    transposition_table: &TranspostionTable,
+    limits: &SearchLimits, 
+    options: &SearchOptions,
+    signals: Arc<SearchSignals>
+) -> SearchMove
 {
     let pieces = count_all_pieces(board);
 
-    let mut transposition_table = TranspostionTable::new();
-    let mut endgame_tablebase = EndGameTablebase::new();
+    let syzygy_move = options.syzygy
+        .as_ref()
+        .and_then(|tablebase| tablebase.best_move(board, history.halfmove_clock()));
 
-    let optimal_move = if pieces <= MAX_PIECE_FOR_ENDGAME
+    let optimal_move = if syzygy_move.is_some()
+    {
+        syzygy_move
+    }
+    else if pieces <= MAX_PIECE_FOR_ENDGAME && options.online_tablebase
     {
-        endgame_tablebase.get_move(board)
+        EndGameTablebase::new().get_move(board)
     }
-    else if plies <= OPENING_FOR_DIFF_EVAL 
+    else if history.plies() < options.book_depth
     {
-        db.get_move(&board)
+        book.get_move(board)
     }
     else 
     {
         None
     };
 
+    // Book and tablebase moves come without a search score
     if let Some(mov) = optimal_move 
     {
-        mov
+        SearchMove::new(Some(mov), 0.0)
     } 
     else 
     {
-        let search_move = alpha_beta
-        (
-            board,
-            if plies <= OPENING_FOR_DIFF_EVAL 
-            {
-                OPENING_SEARCH_DEPTH
-            } 
-            else if pieces <= ENDGAME_PIECE_FOR_GREATER_DEPTH 
-            {
-                ENDGAME_SEARCH_DEPTH
-            } 
-            else 
-            {
-                SEARCH_DEPTH
-            },
-            f32::NEG_INFINITY,
-            f32::INFINITY,
-            board.side_to_move() == Color::White,
-            plies,
-            false,
-            false,
-            &mut transposition_table
-        );
-
-        // let new_board = board.make_move_new(search_move.mov().unwrap());
-        // println!("Eval: {}", eval(board, MoveGen::new_legal(&new_board).collect(), plies, true));
-
-        search_move.mov().unwrap()
+        search_position(board, history, transposition_table, limits, options, signals)
     }
 }
- 
\ No newline at end of file
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+use std::time::Duration;
+use chess::Color;
+use crate::consts::NODE_FLUSH_INTERVAL;
+use crate::eval::white_score;
+use crate::evaluator::{create_evaluator, Evaluator};
+use crate::extension_policy::ExtensionPolicy;
+use crate::game_history::GameHistory;
+use crate::move_ordering::MoveOrdering;
+use crate::pruning_options::PruningOptions;
+use crate::search_limits::SearchLimits;
+use crate::search_options::SearchOptions;
+use crate::search_signals::SearchSignals;
+use crate::syzygy_tablebase::SyzygyTablebase;
+use crate::time_manager::TimeManager;
+use crate::transposition_table::TranspostionTable;
+
+pub struct SearchContext<'a>
+{
+    pub transposition_table: &'a TranspostionTable,
+    pub history: GameHistory,
+    pub evaluator: Box<dyn Evaluator>,
+    pub move_ordering: MoveOrdering,
+    /// Set while a null move cutoff is verified, a search that must not
+    /// lean on null moves itself.
+    pub verifying_null_move: bool,
+    time_manager: TimeManager,
+    options: &'a SearchOptions,
+    signals: Arc<SearchSignals>,
+    node_limit: Option<u64>,
+    root_plies: i32,
+    draw_score: f32,
+    nodes: u64,
+    flushed_nodes: u64,
+    seldepth: u32,
+    pondering: bool,
+    helper: bool,
+    stopped: bool
+}
+
+impl<'a> SearchContext<'a>
+{
+    pub fn new(
+        transposition_table: &'a TranspostionTable,
+        limits: &SearchLimits,
+        side: Color,
+        history: &GameHistory,
+        options: &'a SearchOptions,
+        signals: Arc<SearchSignals>
+    ) -> SearchContext<'a>
+    {
+        SearchContext
+        {
+            transposition_table,
+            time_manager: TimeManager::new(limits, side, options.move_overhead),
+            options,
+            pondering: signals.is_pondering(),
+            signals,
+            node_limit: limits.nodes,
+            root_plies: history.plies(),
+            draw_score: white_score(-options.contempt as f32 / 100.0, side),
+            history: history.clone(),
+            evaluator: create_evaluator(options),
+            move_ordering: MoveOrdering::new(),
+            verifying_null_move: false,
+            nodes: 0,
+            flushed_nodes: 0,
+            seldepth: 0,
+            helper: false,
+            stopped: false
+        }
+    }
+
+    /// Context of a Lazy SMP helper thread, which has no limits of its own and
+    /// searches until the main thread is done.
+    pub fn helper(transposition_table: &'a TranspostionTable, side: Color, history: &GameHistory, options: &'a SearchOptions, signals: Arc<SearchSignals>) -> SearchContext<'a>
+    {
+        let limits = SearchLimits { infinite: true, ..SearchLimits::default() };
+
+        SearchContext
+        {
+            helper: true,
+            pondering: false,
+            ..SearchContext::new(transposition_table, &limits, side, history, options, signals)
+        }
+    }
+
+    pub fn is_main(&self) -> bool
+    {
+        !self.helper
+    }
+
+    pub fn print_info(&self) -> bool
+    {
+        self.options.print_info
+    }
+
+    pub fn extension_policy(&self) -> ExtensionPolicy
+    {
+        self.options.extension_policy
+    }
+
+    pub fn pruning(&self) -> PruningOptions
+    {
+        self.options.pruning
+    }
+
+    pub fn syzygy(&self) -> Option<&'a SyzygyTablebase>
+    {
+        self.options.syzygy.as_deref()
+    }
+
+    /// White relative score of a drawn position, below zero for the side to
+    /// move at the root when `Contempt` is positive.
+    pub fn draw_score(&self) -> f32
+    {
+        self.draw_score
+    }
+
+    /// Plies played in the game before the root position.
+    pub fn root_plies(&self) -> i32
+    {
+        self.root_plies
+    }
+
+    /// Distance from the root of a node at `plies` plies into the game.
+    pub fn ply(&self, plies: i32) -> u32
+    {
+        (plies - self.root_plies).max(0) as u32
+    }
+
+    /// Nodes searched by all threads.
+    pub fn nodes(&self) -> u64
+    {
+        self.signals.nodes() + self.nodes - self.flushed_nodes
+    }
+
+    /// Adds the nodes of this thread to the shared count. Threads only do so
+    /// every `NODE_FLUSH_INTERVAL` nodes and after each iteration, to keep
+    /// them from contending on one counter.
+    pub fn flush_nodes(&mut self)
+    {
+        self.signals.add_nodes(self.nodes - self.flushed_nodes);
+        self.flushed_nodes = self.nodes;
+    }
+
+    pub fn seldepth(&self) -> u32
+    {
+        self.seldepth
+    }
+
+    pub fn elapsed(&self) -> Duration
+    {
+        self.time_manager.elapsed()
+    }
+
+    pub fn stopped(&self) -> bool
+    {
+        self.stopped
+    }
+
+    /// Picks up a `ponderhit`, from which point the clock starts running.
+    fn update_pondering(&mut self)
+    {
+        if self.pondering && !self.signals.is_pondering()
+        {
+            self.pondering = false;
+            self.time_manager.restart();
+        }
+    }
+
+    pub fn should_start_iteration(&mut self) -> bool
+    {
+        self.update_pondering();
+        self.pondering || self.time_manager.should_start_iteration()
+    }
+
+    /// Counts the node being entered and reports whether the search has to
+    /// be abandoned because of a `stop`, the node or the time limit.
+    pub fn enter_node(&mut self, plies: i32) -> bool
+    {
+        self.nodes += 1;
+
+        if self.nodes.is_multiple_of(NODE_FLUSH_INTERVAL)
+        {
+            self.flush_nodes();
+        }
+
+        self.seldepth = self.seldepth.max(self.ply(plies));
+
+        if !self.stopped
+        {
+            self.update_pondering();
+
+            let out_of_nodes = self.node_limit.is_some_and(|limit| self.nodes() > limit);
+            let out_of_time = !self.pondering && self.time_manager.is_out_of_time();
+            let released = self.helper && self.signals.helpers_stopped();
+
+            self.stopped = self.signals.is_stopped() || released || out_of_nodes || out_of_time;
+        }
+
+        self.stopped
+    }
+}
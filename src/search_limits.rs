@@ -0,0 +1,64 @@
+use std::str::FromStr;
+
+#[derive(Clone, Default, Debug)]
+pub struct SearchLimits
+{
+    pub wtime: Option<u64>,
+    pub btime: Option<u64>,
+    pub winc: Option<u64>,
+    pub binc: Option<u64>,
+    pub movestogo: Option<u64>,
+    pub movetime: Option<u64>,
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub infinite: bool,
+    pub ponder: bool,
+    /// The first iteration, for a search that goes on from the depth an
+    /// earlier one completed. Not part of `go`.
+    pub start_depth: Option<u8>
+}
+
+fn parse_next<T: FromStr>(tokens: &mut std::slice::Iter<&str>) -> Option<T>
+{
+    tokens.next().and_then(|token| token.parse::<T>().ok())
+}
+
+impl SearchLimits
+{
+    pub fn from_go_command(input: &str) -> SearchLimits
+    {
+        let mut limits = SearchLimits::default();
+        let tokens = input.split_whitespace().skip(1).collect::<Vec<&str>>();
+        let mut tokens = tokens.iter();
+
+        while let Some(token) = tokens.next()
+        {
+            match *token
+            {
+                "wtime" => limits.wtime = parse_next(&mut tokens),
+                "btime" => limits.btime = parse_next(&mut tokens),
+                "winc" => limits.winc = parse_next(&mut tokens),
+                "binc" => limits.binc = parse_next(&mut tokens),
+                "movestogo" => limits.movestogo = parse_next(&mut tokens),
+                "movetime" => limits.movetime = parse_next(&mut tokens),
+                "depth" => limits.depth = parse_next(&mut tokens),
+                "nodes" => limits.nodes = parse_next(&mut tokens),
+                "infinite" => limits.infinite = true,
+                "ponder" => limits.ponder = true,
+                _ => {}
+            }
+        }
+
+        limits
+    }
+
+    pub fn has_clock(&self) -> bool
+    {
+        self.wtime.is_some() || self.btime.is_some() || self.movetime.is_some()
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        !self.infinite && !self.has_clock() && self.depth.is_none() && self.nodes.is_none()
+    }
+}
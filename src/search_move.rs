@@ -4,7 +4,9 @@ use chess::ChessMove;
 pub struct SearchMove
 {
     mov: Option<ChessMove>,
-    evaluation: f32
+    evaluation: f32,
+    pv: Vec<ChessMove>,
+    depth: u8
 }
 
 impl SearchMove
@@ -19,12 +21,53 @@ impl SearchMove
         self.evaluation
     }
 
+    pub fn pv(&self) -> &[ChessMove]
+    {
+        &self.pv
+    }
+
+    /// Depth of the last completed iteration behind the move, zero for book
+    /// and tablebase moves.
+    pub fn depth(&self) -> u8
+    {
+        self.depth
+    }
+
     pub fn new(mov: Option<ChessMove>, evaluation: f32) -> SearchMove
     {
         SearchMove
         {
             mov,
-            evaluation
+            evaluation,
+            pv: mov.into_iter().collect(),
+            depth: 0
+        }
+    }
+
+    /// The same move and variation scored `evaluation`.
+    pub fn with_eval(self, evaluation: f32) -> SearchMove
+    {
+        SearchMove { evaluation, ..self }
+    }
+
+    pub fn with_depth(self, depth: u8) -> SearchMove
+    {
+        SearchMove { depth, ..self }
+    }
+
+    /// Prepends `mov` to the principal variation of the position it leads to.
+    pub fn with_pv(mov: ChessMove, evaluation: f32, child: &SearchMove) -> SearchMove
+    {
+        let mut pv = Vec::with_capacity(child.pv.len() + 1);
+        pv.push(mov);
+        pv.extend_from_slice(&child.pv);
+
+        SearchMove
+        {
+            mov: Some(mov),
+            evaluation,
+            pv,
+            depth: 0
         }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+use crate::eval_params::EvalParams;
+use crate::extension_policy::ExtensionPolicy;
+use crate::nnue::Network;
+use crate::pruning_options::PruningOptions;
+use crate::syzygy_tablebase::SyzygyTablebase;
+
+/// Engine settings taken from the UCI options when a search starts.
+#[derive(Clone)]
+pub struct SearchOptions
+{
+    pub move_overhead: u64,
+    pub threads: usize,
+    pub syzygy: Option<Arc<SyzygyTablebase>>,
+    pub online_tablebase: bool,
+    pub book_depth: i32,
+    pub contempt: i32,
+    pub eval_params: Arc<EvalParams>,
+    pub network: Option<Arc<Network>>,
+    pub extension_policy: ExtensionPolicy,
+    pub pruning: PruningOptions,
+    pub print_info: bool
+}
+
+impl Default for SearchOptions
+{
+    fn default() -> Self
+    {
+        SearchOptions
+        {
+            move_overhead: 0,
+            threads: 1,
+            syzygy: None,
+            online_tablebase: false,
+            book_depth: 0,
+            contempt: 0,
+            eval_params: Arc::new(EvalParams::default()),
+            network: None,
+            extension_policy: ExtensionPolicy::default(),
+            pruning: PruningOptions::default(),
+            print_info: false
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Flags shared between the UCI thread and the running search threads.
+pub struct SearchSignals
+{
+    stop: AtomicBool,
+    stop_helpers: AtomicBool,
+    pondering: AtomicBool,
+    nodes: AtomicU64
+}
+
+impl SearchSignals
+{
+    pub fn new() -> SearchSignals
+    {
+        SearchSignals
+        {
+            stop: AtomicBool::new(false),
+            stop_helpers: AtomicBool::new(false),
+            pondering: AtomicBool::new(false),
+            nodes: AtomicU64::new(0)
+        }
+    }
+
+    pub fn reset(&self, pondering: bool)
+    {
+        self.stop.store(false, Ordering::Relaxed);
+        self.stop_helpers.store(false, Ordering::Relaxed);
+        self.pondering.store(pondering, Ordering::Relaxed);
+        self.nodes.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stop(&self)
+    {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Ends the helper threads once the main thread has settled on a move,
+    /// without ending an infinite or ponder search.
+    pub fn stop_helpers(&self)
+    {
+        self.stop_helpers.store(true, Ordering::Relaxed);
+    }
+
+    pub fn ponderhit(&self)
+    {
+        self.pondering.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool
+    {
+        self.stop.load(Ordering::Relaxed)
+    }
+
+    pub fn helpers_stopped(&self) -> bool
+    {
+        self.stop_helpers.load(Ordering::Relaxed)
+    }
+
+    pub fn is_pondering(&self) -> bool
+    {
+        self.pondering.load(Ordering::Relaxed)
+    }
+
+    pub fn add_nodes(&self, nodes: u64)
+    {
+        self.nodes.fetch_add(nodes, Ordering::Relaxed);
+    }
+
+    /// Nodes searched by all threads since the last `reset`, as far as they
+    /// have reported them.
+    pub fn nodes(&self) -> u64
+    {
+        self.nodes.load(Ordering::Relaxed)
+    }
+}
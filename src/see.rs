@@ -0,0 +1,153 @@
+use chess::{get_bishop_moves, get_king_moves, get_knight_moves, get_pawn_attacks, get_rook_moves, BitBoard, Board, ChessMove, Color, Piece, Square, ALL_PIECES, EMPTY};
+use crate::consts::SEE_KING_VALUE;
+use crate::material::material;
+
+/// Pieces of both sides attacking `square` through the pieces of `occupied`,
+/// so that sliders behind a piece that left `occupied` come into play.
+fn attackers_to(board: &Board, square: Square, occupied: BitBoard) -> BitBoard
+{
+    let pieces = |piece: Piece| *board.pieces(piece);
+
+    let pawns = get_pawn_attacks(square, Color::Black, pieces(Piece::Pawn) & board.color_combined(Color::White))
+        | get_pawn_attacks(square, Color::White, pieces(Piece::Pawn) & board.color_combined(Color::Black));
+    let diagonals = get_bishop_moves(square, occupied) & (pieces(Piece::Bishop) | pieces(Piece::Queen));
+    let lines = get_rook_moves(square, occupied) & (pieces(Piece::Rook) | pieces(Piece::Queen));
+
+    (pawns
+        | get_knight_moves(square) & pieces(Piece::Knight)
+        | get_king_moves(square) & pieces(Piece::King)
+        | diagonals
+        | lines) & occupied
+}
+
+/// Material `mov` wins once every capture and recapture on its destination
+/// has been played out, each side taking with its least valuable piece and
+/// free to stop. `values` are the piece values by `Piece::to_index`, the
+/// king's is replaced by one no exchange can pay for.
+pub fn see_with_values(board: &Board, mov: ChessMove, values: &[f32; 6]) -> f32
+{
+    let value = |piece: Piece| if piece == Piece::King { SEE_KING_VALUE } else { values[piece.to_index()] };
+
+    let (source, dest) = (mov.get_source(), mov.get_dest());
+    let mut attacker = board.piece_on(source).unwrap_or(Piece::Pawn);
+    let mut occupied = *board.combined() ^ BitBoard::from_square(source);
+
+    let mut gain = [0.0_f32; 32];
+
+    gain[0] = match board.piece_on(dest)
+    {
+        Some(captured) => value(captured),
+        None if attacker == Piece::Pawn && source.get_file() != dest.get_file() =>
+        {
+            occupied ^= BitBoard::from_square(Square::make_square(source.get_rank(), dest.get_file()));
+            value(Piece::Pawn)
+        }
+        None => 0.0
+    };
+
+    if let Some(promotion) = mov.get_promotion()
+    {
+        gain[0] += value(promotion) - value(Piece::Pawn);
+        attacker = promotion;
+    }
+
+    let mut side = !board.side_to_move();
+    let mut depth = 0;
+
+    loop
+    {
+        depth += 1;
+
+        // What the side to move is left with if its last capture is taken back
+        gain[depth] = value(attacker) - gain[depth - 1];
+
+        if (-gain[depth - 1]).max(gain[depth]) < 0.0 || depth == gain.len() - 1
+        {
+            break;
+        }
+
+        let ours = attackers_to(board, dest, occupied) & board.color_combined(side);
+
+        let Some(piece) = ALL_PIECES.into_iter().find(|piece| ours & board.pieces(*piece) != EMPTY)
+        else
+        {
+            break;
+        };
+
+        occupied ^= BitBoard::from_square((ours & board.pieces(piece)).to_square());
+        attacker = piece;
+        side = !side;
+    }
+
+    while depth > 1
+    {
+        depth -= 1;
+        gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
+    }
+
+    gain[0]
+}
+
+/// `see_with_values` with the default material values.
+pub fn see(board: &Board, mov: ChessMove) -> f32
+{
+    see_with_values(board, mov, &ALL_PIECES.map(|piece| material(Some(piece))))
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    fn see_of(fen: &str, mov: &str) -> f32
+    {
+        let board = Board::from_str(fen).unwrap();
+        let mov = ChessMove::from_str(mov).unwrap();
+
+        assert!(board.legal(mov), "{} is illegal in {}", mov, fen);
+        see(&board, mov)
+    }
+
+    fn assert_see(fen: &str, mov: &str, expected: f32)
+    {
+        let value = see_of(fen, mov);
+
+        assert!((value - expected).abs() < 1e-4, "{} in {} is {}, not {}", mov, fen, value, expected);
+    }
+
+    #[test]
+    fn undefended_pieces_are_won()
+    {
+        assert_see("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1", "e4d5", 3.0);
+        assert_see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5", 1.0);
+    }
+
+    #[test]
+    fn rook_takes_pawn_defended_knight()
+    {
+        assert_see("4k3/8/2p5/3n4/8/8/8/3RK3 w - - 0 1", "d1d5", 3.0 - 5.73);
+    }
+
+    #[test]
+    fn exchange_stops_when_recapturing_loses()
+    {
+        // Nxe5 Nxe5 and White stops, Rxe5 Bxe5 Qxe5 Qxe5 would lose the queen
+        assert_see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5", 1.0 - 3.0);
+    }
+
+    #[test]
+    fn x_ray_attackers_join_the_exchange()
+    {
+        // The queen behind the rook keeps Black from recapturing
+        assert_see("3r2k1/8/8/3p4/8/8/3R4/3Q2K1 w - - 0 1", "d2d5", 1.0);
+    }
+
+    #[test]
+    fn en_passant_and_promotion()
+    {
+        assert_see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 1.0);
+        assert_see("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8q", 9.5 - 1.0);
+        assert_see("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q", 5.73 + 9.5 - 1.0);
+    }
+}
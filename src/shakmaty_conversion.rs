@@ -0,0 +1,114 @@
+use std::str::FromStr;
+use chess::{Board, ChessMove, Color, File, Piece, Square};
+use shakmaty::{Bitboard, CastlingMode, Chess, FromSetup, Setup};
+
+pub fn shakmaty_color(color: Color) -> shakmaty::Color
+{
+    match color
+    {
+        Color::White => shakmaty::Color::White,
+        Color::Black => shakmaty::Color::Black
+    }
+}
+
+pub fn shakmaty_role(piece: Piece) -> shakmaty::Role
+{
+    match piece
+    {
+        Piece::Pawn => shakmaty::Role::Pawn,
+        Piece::Knight => shakmaty::Role::Knight,
+        Piece::Bishop => shakmaty::Role::Bishop,
+        Piece::Rook => shakmaty::Role::Rook,
+        Piece::Queen => shakmaty::Role::Queen,
+        Piece::King => shakmaty::Role::King
+    }
+}
+
+pub fn shakmaty_square(square: Square) -> shakmaty::Square
+{
+    shakmaty::Square::new(square.to_int() as u32)
+}
+
+pub fn shakmaty_piece(piece: Piece, color: Color) -> shakmaty::Piece
+{
+    shakmaty::Piece { color: shakmaty_color(color), role: shakmaty_role(piece) }
+}
+
+/// The square a pawn capturing en passant lands on. The chess crate keeps
+/// the square of the pawn that can be taken instead.
+pub fn en_passant_square(board: &Board) -> Option<shakmaty::Square>
+{
+    board.en_passant()
+        .and_then(|square| square.forward(board.side_to_move()))
+        .map(shakmaty_square)
+}
+
+pub fn chess_move(mov: &shakmaty::Move) -> Option<ChessMove>
+{
+    ChessMove::from_str(&mov.to_uci(CastlingMode::Standard).to_string()).ok()
+}
+
+/// `board` as a shakmaty position, with the fifty-move rule counter the
+/// chess crate doesn't keep.
+pub fn shakmaty_position(board: &Board, halfmove_clock: u32) -> Option<Chess>
+{
+    let mut setup = Setup::empty();
+
+    for square in *board.combined()
+    {
+        let (piece, color) = (board.piece_on(square)?, board.color_on(square)?);
+        setup.board.set_piece_at(shakmaty_square(square), shakmaty_piece(piece, color));
+    }
+
+    for color in [Color::White, Color::Black]
+    {
+        let rights = board.castle_rights(color);
+        let rook = |file| Bitboard::from(shakmaty_square(Square::make_square(color.to_my_backrank(), file)));
+
+        if rights.has_kingside()
+        {
+            setup.castling_rights |= rook(File::H);
+        }
+
+        if rights.has_queenside()
+        {
+            setup.castling_rights |= rook(File::A);
+        }
+    }
+
+    setup.turn = shakmaty_color(board.side_to_move());
+    setup.ep_square = en_passant_square(board);
+    setup.halfmoves = halfmove_clock;
+
+    Chess::from_setup(setup, CastlingMode::Standard).ok()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use shakmaty::fen::Fen;
+    use shakmaty::EnPassantMode;
+    use super::*;
+
+    #[test]
+    fn position_matches_fen()
+    {
+        let fens =
+        [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 7 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 1",
+            "8/8/8/8/1k1Pp3/8/8/4K3 b - d3 0 1",
+            "8/2k5/8/8/8/8/5KR1/8 w - - 37 1"
+        ];
+
+        for fen in fens
+        {
+            let board = Board::from_str(fen).unwrap();
+            let clock = fen.split_whitespace().nth(4).unwrap().parse().unwrap();
+            let position = shakmaty_position(&board, clock).unwrap();
+
+            assert_eq!(Fen::from_position(&position, EnPassantMode::Legal).to_string(), fen);
+        }
+    }
+}
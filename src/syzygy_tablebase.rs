@@ -0,0 +1,74 @@
+use std::env;
+use chess::{Board, CastleRights, ChessMove, Color};
+use shakmaty::Chess;
+use shakmaty_syzygy::{Tablebase, Wdl};
+use crate::shakmaty_conversion::{chess_move, shakmaty_position};
+
+/// Syzygy WDL/DTZ tables probed straight from the `.rtbw`/`.rtbz` files.
+pub struct SyzygyTablebase
+{
+    tables: Tablebase<Chess>
+}
+
+impl SyzygyTablebase
+{
+    /// Loads every table found in the directories of `path`, separated like
+    /// the `PATH` environment variable.
+    pub fn load(path: &str) -> Result<SyzygyTablebase, String>
+    {
+        let mut tables = Tablebase::new();
+        let mut found = 0;
+
+        for directory in env::split_paths(path)
+        {
+            found += tables
+                .add_directory(&directory)
+                .map_err(|err| format!("can't read Syzygy tables from {}: {}", directory.display(), err))?;
+        }
+
+        if found == 0
+        {
+            return Err(format!("no Syzygy tables found in {}", path));
+        }
+
+        Ok(SyzygyTablebase { tables })
+    }
+
+    pub fn max_pieces(&self) -> u32
+    {
+        self.tables.max_pieces() as u32
+    }
+
+    fn can_probe(&self, board: &Board) -> bool
+    {
+        board.combined().popcnt() <= self.max_pieces()
+            && board.castle_rights(Color::White) == CastleRights::NoRights
+            && board.castle_rights(Color::Black) == CastleRights::NoRights
+    }
+
+    /// Win/draw/loss for the side to move. Only probed right after a capture
+    /// or pawn move, where the WDL tables are exact without knowing how far
+    /// the fifty-move rule has run.
+    pub fn probe_wdl(&self, board: &Board, halfmove_clock: u32) -> Option<Wdl>
+    {
+        if halfmove_clock != 0 || !self.can_probe(board)
+        {
+            return None;
+        }
+
+        self.tables.probe_wdl_after_zeroing(&shakmaty_position(board, halfmove_clock)?).ok()
+    }
+
+    /// The move keeping the best outcome under the fifty-move rule, ranked by DTZ.
+    pub fn best_move(&self, board: &Board, halfmove_clock: u32) -> Option<ChessMove>
+    {
+        if !self.can_probe(board)
+        {
+            return None;
+        }
+
+        let (mov, _) = self.tables.best_move(&shakmaty_position(board, halfmove_clock)?).ok()??;
+
+        chess_move(&mov)
+    }
+}
@@ -0,0 +1,139 @@
+use std::time::{Duration, Instant};
+use chess::Color;
+use crate::consts::{DEFAULT_MOVES_TO_GO, MAX_TIME_FACTOR, MIN_THINKING_SHARE, MIN_THINKING_TIME};
+use crate::search_limits::SearchLimits;
+
+/// `time` less the overhead, but at least a `MIN_THINKING_SHARE`th of it: an
+/// overhead as large as the clock would otherwise leave a flat minimum.
+fn usable_time(time: u64, move_overhead: u64) -> u64
+{
+    time.saturating_sub(move_overhead).max(time / MIN_THINKING_SHARE).max(MIN_THINKING_TIME)
+}
+
+pub struct TimeManager
+{
+    start: Instant,
+    optimum: Option<Duration>,
+    maximum: Option<Duration>
+}
+
+impl TimeManager
+{
+    pub fn new(limits: &SearchLimits, side: Color, move_overhead: u64) -> TimeManager
+    {
+        let start = Instant::now();
+
+        if limits.infinite
+        {
+            return TimeManager { start, optimum: None, maximum: None };
+        }
+
+        if let Some(movetime) = limits.movetime
+        {
+            let movetime = Duration::from_millis(usable_time(movetime, move_overhead));
+            return TimeManager { start, optimum: Some(movetime), maximum: Some(movetime) };
+        }
+
+        let (time, inc) = match side
+        {
+            Color::White => (limits.wtime, limits.winc.unwrap_or(0)),
+            Color::Black => (limits.btime, limits.binc.unwrap_or(0)),
+        };
+
+        match time
+        {
+            Some(time) =>
+            {
+                let available = usable_time(time, move_overhead);
+                let moves_to_go = limits.movestogo.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+                let maximum = (available * 8 / 10).max(MIN_THINKING_TIME);
+                let optimum = (available / moves_to_go + inc * 3 / 4).max(MIN_THINKING_TIME).min(maximum);
+                let maximum = (optimum * MAX_TIME_FACTOR).min(maximum);
+
+                TimeManager
+                {
+                    start,
+                    optimum: Some(Duration::from_millis(optimum)),
+                    maximum: Some(Duration::from_millis(maximum))
+                }
+            }
+            None => TimeManager { start, optimum: None, maximum: None }
+        }
+    }
+
+    /// Starts the clock again, used when a ponder search turns into a real one.
+    pub fn restart(&mut self)
+    {
+        self.start = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration
+    {
+        self.start.elapsed()
+    }
+
+    /// Checked between iterations: the next iteration is unlikely to finish
+    /// once half of the optimum time has been spent.
+    pub fn should_start_iteration(&self) -> bool
+    {
+        match self.optimum
+        {
+            Some(optimum) => self.elapsed() < optimum / 2,
+            None => true
+        }
+    }
+
+    /// Checked inside the search, aborts the running iteration.
+    pub fn is_out_of_time(&self) -> bool
+    {
+        match self.maximum
+        {
+            Some(maximum) => self.elapsed() >= maximum,
+            None => false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn limits(go: &str) -> SearchLimits
+    {
+        SearchLimits::from_go_command(go)
+    }
+
+    #[test]
+    fn movetime_leaves_the_overhead()
+    {
+        let manager = TimeManager::new(&limits("go movetime 5000"), Color::White, 100);
+        assert_eq!(manager.maximum, Some(Duration::from_millis(4900)));
+
+        let manager = TimeManager::new(&limits("go movetime 1000"), Color::White, 2000);
+        assert_eq!(manager.maximum, Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn overhead_larger_than_the_clock_keeps_a_share_of_it()
+    {
+        let short = TimeManager::new(&limits("go wtime 1000 btime 60000"), Color::White, 2000);
+        let shorter = TimeManager::new(&limits("go wtime 400 btime 60000"), Color::White, 2000);
+
+        assert!(short.maximum > shorter.maximum);
+        assert!(shorter.optimum.unwrap() >= Duration::from_millis(MIN_THINKING_TIME));
+    }
+
+    #[test]
+    fn clock_is_split_over_the_moves_to_go()
+    {
+        let manager = TimeManager::new(&limits("go wtime 60000 btime 1000 winc 1000 movestogo 20"), Color::White, 0);
+
+        assert_eq!(manager.optimum, Some(Duration::from_millis(60000 / 20 + 750)));
+        assert_eq!(manager.maximum, Some(Duration::from_millis((60000 / 20 + 750) * MAX_TIME_FACTOR)));
+
+        let manager = TimeManager::new(&limits("go infinite"), Color::Black, 0);
+        assert!(manager.maximum.is_none() && manager.should_start_iteration() && !manager.is_out_of_time());
+    }
+}
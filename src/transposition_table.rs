@@ -1,29 +1,357 @@
-use std::collections::HashMap;
-use chess::Board;
-use crate::search_move::SearchMove;
+use std::mem::size_of;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use chess::{Board, ChessMove, Piece, ALL_SQUARES};
+use crate::consts::MATE_THRESHOLD;
 
-pub struct TranspostionTable 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bound
 {
-    table: HashMap<u64, SearchMove>
+    Exact,
+    Lower,
+    Upper
 }
 
-impl TranspostionTable 
+#[derive(Clone, Copy)]
+pub struct TranspositionEntry
 {
-    pub fn new() -> Self
+    key: u64,
+    depth: u8,
+    bound: Bound,
+    mov: Option<ChessMove>,
+    evaluation: f32,
+    age: u8
+}
+
+impl TranspositionEntry
+{
+    pub fn depth(&self) -> u8
+    {
+        self.depth
+    }
+
+    pub fn bound(&self) -> Bound
+    {
+        self.bound
+    }
+
+    pub fn mov(&self) -> Option<ChessMove>
+    {
+        self.mov
+    }
+
+    /// Mate scores are stored relative to the node, this turns them back into
+    /// distances from the root of the current search.
+    pub fn eval(&self, ply: u32) -> f32
+    {
+        if self.evaluation >= MATE_THRESHOLD
+        {
+            self.evaluation - ply as f32
+        }
+        else if self.evaluation <= -MATE_THRESHOLD
+        {
+            self.evaluation + ply as f32
+        }
+        else
+        {
+            self.evaluation
+        }
+    }
+}
+
+const AGE_MASK: u8 = 0x3F;
+
+fn encode_move(mov: Option<ChessMove>) -> u64
+{
+    match mov
+    {
+        Some(mov) =>
+        {
+            let promotion = match mov.get_promotion()
+            {
+                Some(Piece::Knight) => 1,
+                Some(Piece::Bishop) => 2,
+                Some(Piece::Rook) => 3,
+                Some(Piece::Queen) => 4,
+                _ => 0
+            };
+
+            mov.get_source().to_index() as u64 | (mov.get_dest().to_index() as u64) << 6 | promotion << 12
+        }
+        None => 0
+    }
+}
+
+fn decode_move(bits: u64) -> Option<ChessMove>
+{
+    let source = (bits & 0x3F) as u8;
+    let dest = ((bits >> 6) & 0x3F) as u8;
+
+    // A move never starts and ends on the same square, so a1a1 encodes "no move"
+    if source == dest
+    {
+        return None;
+    }
+
+    let promotion = match (bits >> 12) & 0x7
+    {
+        1 => Some(Piece::Knight),
+        2 => Some(Piece::Bishop),
+        3 => Some(Piece::Rook),
+        4 => Some(Piece::Queen),
+        _ => None
+    };
+
+    Some(ChessMove::new(ALL_SQUARES[source as usize], ALL_SQUARES[dest as usize], promotion))
+}
+
+impl TranspositionEntry
+{
+    /// Layout: move in bits 0-15, evaluation in 16-47, depth in 48-55,
+    /// bound in 56-57 (never zero, so an empty slot is all zeroes) and age in 58-63.
+    fn pack(&self) -> u64
+    {
+        let bound = match self.bound
+        {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3
+        };
+
+        encode_move(self.mov)
+            | (self.evaluation.to_bits() as u64) << 16
+            | (self.depth as u64) << 48
+            | bound << 56
+            | ((self.age & AGE_MASK) as u64) << 58
+    }
+
+    fn unpack(key: u64, data: u64) -> Option<TranspositionEntry>
+    {
+        let bound = match (data >> 56) & 0x3
+        {
+            1 => Bound::Exact,
+            2 => Bound::Lower,
+            3 => Bound::Upper,
+            _ => return None
+        };
+
+        Some(TranspositionEntry
+        {
+            key,
+            depth: (data >> 48) as u8,
+            bound,
+            mov: decode_move(data & 0xFFFF),
+            evaluation: f32::from_bits((data >> 16) as u32),
+            age: (data >> 58) as u8
+        })
+    }
+}
+
+/// Slots are written without locking by every search thread. The key is
+/// stored xor-ed with the data, so a slot torn by two concurrent writers
+/// fails the key check instead of returning another position's entry.
+struct TranspositionSlot
+{
+    key: AtomicU64,
+    data: AtomicU64
+}
+
+pub struct TranspostionTable
+{
+    entries: Vec<TranspositionSlot>,
+    age: AtomicU8
+}
+
+impl TranspostionTable
+{
+    pub fn new(size_mb: usize) -> Self
+    {
+        let entry_count = (size_mb * 1024 * 1024 / size_of::<TranspositionSlot>()).max(1);
+
+        TranspostionTable
+        {
+            entries: (0..entry_count)
+                .map(|_| TranspositionSlot { key: AtomicU64::new(0), data: AtomicU64::new(0) })
+                .collect(),
+            age: AtomicU8::new(0)
+        }
+    }
+
+    pub fn clear(&self)
+    {
+        for slot in &self.entries
+        {
+            slot.key.store(0, Ordering::Relaxed);
+            slot.data.store(0, Ordering::Relaxed);
+        }
+
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Called once per `go`, older entries become the first to be replaced.
+    pub fn new_search(&self)
+    {
+        let age = self.age.load(Ordering::Relaxed);
+        self.age.store(age.wrapping_add(1) & AGE_MASK, Ordering::Relaxed);
+    }
+
+    fn index(&self, key: u64) -> usize
+    {
+        (key % self.entries.len() as u64) as usize
+    }
+
+    fn load(&self, index: usize) -> Option<TranspositionEntry>
+    {
+        let slot = &self.entries[index];
+        let data = slot.data.load(Ordering::Relaxed);
+        let key = slot.key.load(Ordering::Relaxed) ^ data;
+
+        TranspositionEntry::unpack(key, data)
+    }
+
+    /// Occupancy in permille, sampled over the first thousand slots as UCI
+    /// `hashfull` allows.
+    pub fn hashfull(&self) -> usize
+    {
+        let sample = self.entries.len().min(1000);
+        let age = self.age.load(Ordering::Relaxed);
+
+        let used = (0..sample)
+            .filter(|index| self.load(*index).is_some_and(|entry| entry.age == age))
+            .count();
+
+        used * 1000 / sample
+    }
+
+    pub fn get_position(&self, board: &Board) -> Option<TranspositionEntry>
+    {
+        let key = board.get_hash();
+
+        self.load(self.index(key)).filter(|entry| entry.key == key)
+    }
+
+    /// Depth-preferred replacement: a slot is only overwritten by a deeper
+    /// result, unless it belongs to this position or to an earlier search.
+    pub fn add_position(&self, board: &Board, depth: u8, bound: Bound, mov: Option<ChessMove>, evaluation: f32, ply: u32)
+    {
+        let key = board.get_hash();
+        let index = self.index(key);
+        let age = self.age.load(Ordering::Relaxed);
+
+        if let Some(entry) = self.load(index)
+        {
+            if entry.key != key && entry.age == age && entry.depth > depth
+            {
+                return;
+            }
+        }
+
+        let evaluation = if evaluation >= MATE_THRESHOLD
+        {
+            evaluation + ply as f32
+        }
+        else if evaluation <= -MATE_THRESHOLD
+        {
+            evaluation - ply as f32
+        }
+        else
+        {
+            evaluation
+        };
+
+        let data = TranspositionEntry
+        {
+            key,
+            depth,
+            bound,
+            mov,
+            evaluation,
+            age
+        }.pack();
+
+        let slot = &self.entries[index];
+        slot.key.store(key ^ data, Ordering::Relaxed);
+        slot.data.store(data, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::str::FromStr;
+    use super::*;
+
+    fn entry(mov: Option<ChessMove>, evaluation: f32, bound: Bound) -> TranspositionEntry
     {
-        TranspostionTable 
+        TranspositionEntry { key: 0x1234_5678_9ABC_DEF0, depth: 17, bound, mov, evaluation, age: 42 }
+    }
+
+    #[test]
+    fn pack_unpack_round_trip()
+    {
+        let moves = [None, Some(ChessMove::from_str("e2e4").unwrap()), Some(ChessMove::from_str("a7a8n").unwrap()), Some(ChessMove::from_str("h2h1q").unwrap())];
+
+        for mov in moves
         {
-            table: HashMap::new()
+            for bound in [Bound::Exact, Bound::Lower, Bound::Upper]
+            {
+                let original = entry(mov, -3.25, bound);
+                let unpacked = TranspositionEntry::unpack(original.key, original.pack()).unwrap();
+
+                assert_eq!(unpacked.key, original.key);
+                assert_eq!(unpacked.depth, original.depth);
+                assert_eq!(unpacked.bound, original.bound);
+                assert_eq!(unpacked.mov, original.mov);
+                assert_eq!(unpacked.evaluation, original.evaluation);
+                assert_eq!(unpacked.age, original.age);
+            }
         }
     }
 
-    pub fn get_position(&self, board: &Board) -> Option<&SearchMove> 
+    #[test]
+    fn empty_slot_is_no_entry()
     {
-        self.table.get(&board.get_hash())
+        assert!(TranspositionEntry::unpack(0, 0).is_none());
+        assert_eq!(decode_move(encode_move(None)), None);
     }
 
-    pub fn add_position(&mut self, board: &Board, mov: &SearchMove) 
+    #[test]
+    fn mate_scores_are_stored_relative_to_the_node()
     {
-        self.table.insert(board.get_hash(), mov.clone());
+        let table = TranspostionTable::new(1);
+        let board = Board::default();
+        let mate_in_three = MATE_THRESHOLD + 100.0;
+
+        // Found 4 plies from the root, the mate is 4 plies closer to the node
+        table.add_position(&board, 5, Bound::Exact, None, mate_in_three, 4);
+
+        let stored = table.get_position(&board).unwrap();
+        assert_eq!(stored.eval(4), mate_in_three);
+        assert_eq!(stored.eval(10), mate_in_three - 6.0);
+
+        table.add_position(&board, 5, Bound::Exact, None, -mate_in_three, 4);
+        assert_eq!(table.get_position(&board).unwrap().eval(2), -mate_in_three - 2.0);
+
+        table.add_position(&board, 5, Bound::Exact, None, 1.5, 4);
+        assert_eq!(table.get_position(&board).unwrap().eval(10), 1.5);
+    }
+
+    #[test]
+    fn deeper_entries_of_the_current_search_are_kept()
+    {
+        let table = TranspostionTable::new(1);
+        let board = Board::default();
+        let other = Board::from_str("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+
+        // Both keys land in one slot of a table with a single entry
+        let table = TranspostionTable { entries: table.entries.into_iter().take(1).collect(), age: AtomicU8::new(0) };
+
+        table.add_position(&board, 8, Bound::Exact, None, 0.5, 0);
+        table.add_position(&other, 3, Bound::Exact, None, 0.5, 0);
+        assert!(table.get_position(&board).is_some());
+
+        table.new_search();
+        table.add_position(&other, 3, Bound::Exact, None, 0.5, 0);
+        assert!(table.get_position(&board).is_none());
+        assert!(table.get_position(&other).is_some());
     }
 }
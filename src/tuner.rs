@@ -0,0 +1,210 @@
+use std::fs;
+use std::sync::Arc;
+use std::thread;
+use chess::{Board, MoveGen};
+use crate::consts::{TUNE_MAX_PASSES, TUNE_STEP};
+use crate::eval::eval;
+use crate::eval_params::EvalParams;
+use crate::game_history::GameHistory;
+use crate::search::quiet_position;
+use crate::search_context::SearchContext;
+use crate::search_limits::SearchLimits;
+use crate::search_options::SearchOptions;
+use crate::search_signals::SearchSignals;
+use crate::transposition_table::TranspostionTable;
+use crate::uci::parse_fen;
+
+/// Both sides always keep their king, so its value cancels out of every
+/// evaluation and can't be fitted.
+const UNTUNED_WEIGHTS: [&str; 2] = ["king_value_mg", "king_value_eg"];
+
+/// A labelled position, already resolved to the end of its quiescence
+/// search so that the tuner only needs the static evaluation.
+struct TuningPosition
+{
+    board: Board,
+    plies: i32,
+    result: f32
+}
+
+/// The game result from White's side, written as `1-0`, `1/2-1/2`, `½-½`,
+/// `0-1` or as the score itself.
+fn parse_result(token: &str) -> Option<f32>
+{
+    match token.trim_matches(|character| "[]\"();,".contains(character))
+    {
+        "1-0" | "1.0" | "1" => Some(1.0),
+        "1/2-1/2" | "½-½" | "1/2" | "½" | "0.5" => Some(0.5),
+        "0-1" | "0.0" | "0" => Some(0.0),
+        _ => None
+    }
+}
+
+/// A FEN, with or without its move counters, followed by the result as the
+/// last token of the line.
+fn parse_line(line: &str) -> Option<(Board, GameHistory, f32)>
+{
+    let tokens = line.split_whitespace().collect::<Vec<&str>>();
+
+    if tokens.len() < 5
+    {
+        return None;
+    }
+
+    let result = parse_result(tokens[tokens.len() - 1])?;
+    let counters = tokens[4..tokens.len() - 1]
+        .iter()
+        .take_while(|token| token.parse::<u32>().is_ok())
+        .take(2)
+        .copied();
+
+    let fen = tokens[..4].iter().copied().chain(counters).collect::<Vec<&str>>().join(" ");
+    let (board, history) = parse_fen(&fen).ok()?;
+
+    Some((board, history, result))
+}
+
+fn load_dataset(path: &str) -> Result<Vec<TuningPosition>, String>
+{
+    let text = fs::read_to_string(path).map_err(|err| format!("can't read {}: {}", path, err))?;
+
+    let transposition_table = TranspostionTable::new(1);
+    let options = SearchOptions::default();
+    let limits = SearchLimits { infinite: true, ..SearchLimits::default() };
+    let signals = Arc::new(SearchSignals::new());
+
+    let mut positions = Vec::new();
+
+    for (board, history, result) in text.lines().filter_map(parse_line)
+    {
+        let mut ctx = SearchContext::new(&transposition_table, &limits, board.side_to_move(), &history, &options, signals.clone());
+        let (board, plies) = quiet_position(&board, history.plies(), &mut ctx);
+
+        // Mates carry no information about the weights
+        if eval(&board, MoveGen::new_legal(&board).collect(), plies, &options.eval_params).is_finite()
+        {
+            positions.push(TuningPosition { board, plies, result });
+        }
+    }
+
+    Ok(positions)
+}
+
+/// Expected score of White for an evaluation in pawns.
+fn sigmoid(score: f32, k: f32) -> f64
+{
+    1.0 / (1.0 + 10f64.powf(-(k * score) as f64 / 4.0))
+}
+
+fn mean_squared_error(positions: &[TuningPosition], params: &EvalParams, k: f32) -> f64
+{
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = positions.len().div_ceil(threads).max(1);
+
+    let total = thread::scope(|scope|
+    {
+        let workers = positions
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move ||
+            {
+                chunk
+                    .iter()
+                    .map(|position|
+                    {
+                        let score = eval(&position.board, MoveGen::new_legal(&position.board).collect(), position.plies, params);
+                        (position.result as f64 - sigmoid(score, k)).powi(2)
+                    })
+                    .sum::<f64>()
+            }))
+            .collect::<Vec<_>>();
+
+        workers.into_iter().map(|worker| worker.join().unwrap()).sum::<f64>()
+    });
+
+    total / positions.len().max(1) as f64
+}
+
+/// The sigmoid scaling that best fits the current evaluation, searched
+/// first in steps of 0.1 then of 0.01.
+fn find_k(positions: &[TuningPosition], params: &EvalParams) -> f32
+{
+    let best_in = |candidates: Vec<f32>|
+    {
+        candidates
+            .into_iter()
+            .map(|k| (k, mean_squared_error(positions, params, k)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(1.0, |(k, _)| k)
+    };
+
+    let coarse = best_in((1..=30).map(|step| step as f32 * 0.1).collect());
+    best_in((-10..=10).map(|step| coarse + step as f32 * 0.01).filter(|k| *k > 0.0).collect())
+}
+
+/// Texel tuning: every weight in turn is moved by one step up or down as
+/// long as that lowers the error, until a whole pass changes nothing. The
+/// parameters are saved to `output` after every pass.
+pub fn run_tune(dataset: &str, output: &str, passes: Option<u32>)
+{
+    let positions = match load_dataset(dataset)
+    {
+        Ok(positions) => positions,
+        Err(err) =>
+        {
+            eprintln!("{}", err);
+            return;
+        }
+    };
+
+    println!("{} positions", positions.len());
+
+    if positions.is_empty()
+    {
+        return;
+    }
+
+    let mut params = EvalParams::default();
+    let k = find_k(&positions, &params);
+    let mut best_error = mean_squared_error(&positions, &params, k);
+
+    println!("K {:.2}, initial error {:.6}", k, best_error);
+
+    for pass in 1..=passes.unwrap_or(TUNE_MAX_PASSES)
+    {
+        let mut improved = false;
+
+        for name in EvalParams::weight_names().into_iter().filter(|name| !UNTUNED_WEIGHTS.contains(&name.as_str()))
+        {
+            for step in [TUNE_STEP, -TUNE_STEP]
+            {
+                let mut candidate = params.clone();
+                *candidate.weight_mut(&name).unwrap() += step;
+
+                let error = mean_squared_error(&positions, &candidate, k);
+
+                if error < best_error
+                {
+                    params = candidate;
+                    best_error = error;
+                    improved = true;
+                    break;
+                }
+            }
+        }
+
+        println!("pass {}: error {:.6}", pass, best_error);
+
+        if let Err(err) = params.save(output)
+        {
+            eprintln!("{}", err);
+            return;
+        }
+
+        if !improved
+        {
+            break;
+        }
+    }
+
+    println!("parameters written to {}", output);
+}
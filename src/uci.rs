@@ -2,12 +2,28 @@ use std::collections::HashMap;
 use std::str::FromStr;
 use std::io::{self, BufRead, Write};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use chess::{Board, BoardStatus, ChessMove};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use chess::{Board, BoardStatus, ChessMove, Color};
+use crate::book_learning::BookLearner;
 use crate::consts::OPENING_BOOK_MAX_PLIES;
+use crate::eval::trace_eval;
+use crate::eval_params::{EvalParams, EVAL_OPTION_PREFIX};
+use crate::evaluator::nnue_eval;
+use crate::extension_policy::ExtensionPolicy;
+use crate::game_history::GameHistory;
 use crate::move_database::MoveDatabase;
 use crate::move_string_conversion::move_to_string;
+use crate::nnue::Network;
+use crate::perft::print_divide;
 use crate::search::engine;
+use crate::search_limits::SearchLimits;
+use crate::polyglot_book::PolyglotBook;
+use crate::pruning_options::PruningOptions;
+use crate::search_options::SearchOptions;
+use crate::search_signals::SearchSignals;
+use crate::syzygy_tablebase::SyzygyTablebase;
+use crate::transposition_table::TranspostionTable;
 
 #[derive(Debug, Clone)]
 pub struct ExtendedOption {
@@ -16,6 +32,7 @@ pub struct ExtendedOption {
     pub min: Option<i32>,
     pub max: Option<i32>,
     pub value: Option<String>,
+    pub hidden: bool,
 }
 
 impl ExtendedOption {
@@ -26,30 +43,129 @@ impl ExtendedOption {
             min,
             max,
             value: None,
+            hidden: false,
         }
     }
 
-    pub fn set_value(&mut self, value: &str) {
-        if let Some(min) = self.min {
-            if let Some(max) = self.max {
-                let value_int = value.parse::<i32>().unwrap_or(min - 1);
-                if value_int < min || value_int > max {
-                    panic!("Value {} is out of bounds ({:?} to {:?})", value_int, min, max);
-                }
-            }
-        }
-        self.value = Some(value.to_string());
+    /// Settable with `setoption` but left out of the `uci` listing, for
+    /// switches meant for testing rather than for users.
+    pub fn hidden(mut self) -> Self {
+        self.hidden = true;
+        self
+    }
+
+    /// Spin values outside the bounds are clamped to them, values that
+    /// aren't numbers leave the option as it was.
+    pub fn set_value(&mut self, value: &str) -> Result<(), String> {
+        let value = match (self.min, self.max) {
+            (Some(min), Some(max)) => value
+                .parse::<i32>()
+                .map_err(|_| format!("invalid value {}", value))?
+                .clamp(min, max)
+                .to_string(),
+            _ => value.to_string(),
+        };
+
+        self.value = Some(value);
+        Ok(())
+    }
+
+    pub fn string_value(&self) -> String {
+        self.value
+            .as_ref()
+            .or(self.default.as_ref())
+            .filter(|value| value.as_str() != "<empty>")
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn bool_value(&self) -> bool {
+        self.string_value() == "true"
+    }
+
+    pub fn int_value(&self) -> i32 {
+        self.value
+            .as_ref()
+            .or(self.default.as_ref())
+            .and_then(|value| value.parse::<i32>().ok())
+            .unwrap_or(0)
+    }
+}
+
+fn stop_search(signals: &SearchSignals, search_thread: &mut Option<JoinHandle<()>>) {
+    if let Some(handle) = search_thread.take() {
+        signals.stop();
+        handle.join().expect("Search thread panicked");
     }
 }
 
+/// A margin option in centipawns as pawns, zero turning it off.
+fn centipawn_margin(centipawns: i32) -> Option<f32> {
+    (centipawns > 0).then(|| centipawns as f32 / 100.0)
+}
+
+/// The halfmove clock and the plies played before `board`, from the last two
+/// FEN fields when present.
+fn fen_counters(fen: &str, board: &Board) -> (u32, i32) {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+
+    let halfmove_clock = fields.get(4).and_then(|field| field.parse::<u32>().ok()).unwrap_or(0);
+    let fullmove_number = fields.get(5).and_then(|field| field.parse::<i32>().ok()).unwrap_or(1).max(1);
+    let black_to_move = if board.side_to_move() == Color::Black { 1 } else { 0 };
+
+    (halfmove_clock, (fullmove_number - 1) * 2 + black_to_move)
+}
+
+/// Parses a FEN along with the halfmove clock and move number it carries.
+pub fn parse_fen(fen: &str) -> Result<(Board, GameHistory), String> {
+    let board = Board::from_str(fen).map_err(|err| format!("invalid fen {}: {}", fen, err))?;
+    let (halfmove_clock, plies) = fen_counters(fen, &board);
+
+    Ok((board, GameHistory::new(&board, halfmove_clock, plies)))
+}
+
+/// Parses a `position [startpos | fen <fen>] [moves <moves>]` command,
+/// checking every move for legality.
+fn parse_position(input: &str) -> Result<(Board, GameHistory), String> {
+    let (setup, moves) = match input.find("moves") {
+        Some(moves_index) => (&input[..moves_index], input[moves_index + "moves".len()..].split_whitespace().collect()),
+        None => (input, Vec::new()),
+    };
+
+    let (mut board, mut history) = if let Some(fen_index) = setup.find("fen") {
+        parse_fen(setup[fen_index + "fen".len()..].trim())?
+    } else if setup.contains("startpos") {
+        (Board::default(), GameHistory::new(&Board::default(), 0, 0))
+    } else {
+        return Err("position needs startpos or fen".to_string());
+    };
+
+    for mv_str in moves {
+        let mv = ChessMove::from_str(mv_str)
+            .ok()
+            .filter(|mv| board.legal(*mv))
+            .ok_or_else(|| format!("illegal move {} in {}", mv_str, board))?;
+
+        let next_board = board.make_move_new(mv);
+        history.push(&board, mv, &next_board);
+        board = next_board;
+    }
+
+    Ok((board, history))
+}
+
 pub fn start_uci() {
     let cloned_db = Arc::new(Mutex::new(MoveDatabase::load()));
+    let learner = BookLearner::start(cloned_db.clone());
 
     let stdin = io::stdin();
     let mut stdout = io::stdout();
 
     let mut board = Board::default();
-    let mut plies = 0;
+    let mut history = GameHistory::new(&board, 0, 0);
+
+    let signals = Arc::new(SearchSignals::new());
+    let mut search_thread: Option<JoinHandle<()>> = None;
 
     let mut options: HashMap<String, ExtendedOption> = HashMap::new();
 
@@ -57,6 +173,29 @@ pub fn start_uci() {
     options.insert("Hash".to_string(), ExtendedOption::new("spin", Some("64"), Some(1), Some(2048)));
     options.insert("Threads".to_string(), ExtendedOption::new("spin", Some("1"), Some(1), Some(16)));
     options.insert("Move Overhead".to_string(), ExtendedOption::new("spin", Some("2000"), Some(0), Some(10000)));
+    options.insert("SyzygyPath".to_string(), ExtendedOption::new("string", Some("<empty>"), None, None));
+    options.insert("SyzygyOnline".to_string(), ExtendedOption::new("check", Some("false"), None, None));
+    options.insert("BookFile".to_string(), ExtendedOption::new("string", Some("<empty>"), None, None));
+    options.insert("BookBestMove".to_string(), ExtendedOption::new("check", Some("false"), None, None));
+    options.insert("BookDepth".to_string(), ExtendedOption::new("spin", Some("16"), Some(0), Some(256)));
+    options.insert("BookLearning".to_string(), ExtendedOption::new("check", Some("true"), None, None));
+    options.insert("BookLearningDepth".to_string(), ExtendedOption::new("spin", Some("12"), Some(1), Some(64)));
+    options.insert("Contempt".to_string(), ExtendedOption::new("spin", Some("0"), Some(-100), Some(100)));
+    options.insert("SwingExtension".to_string(), ExtendedOption::new("spin", Some("150"), Some(0), Some(1000)));
+    options.insert("SwingReduction".to_string(), ExtendedOption::new("spin", Some("100"), Some(0), Some(1000)));
+    options.insert("NullMovePruning".to_string(), ExtendedOption::new("check", Some("true"), None, None).hidden());
+    options.insert("LateMoveReductions".to_string(), ExtendedOption::new("check", Some("true"), None, None).hidden());
+    options.insert("ReverseFutility".to_string(), ExtendedOption::new("check", Some("true"), None, None).hidden());
+    options.insert("FutilityPruning".to_string(), ExtendedOption::new("check", Some("true"), None, None).hidden());
+    options.insert("Razoring".to_string(), ExtendedOption::new("check", Some("true"), None, None).hidden());
+    options.insert("LateMovePruning".to_string(), ExtendedOption::new("check", Some("true"), None, None).hidden());
+    options.insert("EvalParamsFile".to_string(), ExtendedOption::new("string", Some("<empty>"), None, None));
+    options.insert("EvalFile".to_string(), ExtendedOption::new("string", Some("<empty>"), None, None));
+
+    let mut transposition_table = Arc::new(TranspostionTable::new(options["Hash"].int_value() as usize));
+    let mut syzygy: Option<Arc<SyzygyTablebase>> = None;
+    let mut eval_params = Arc::new(EvalParams::default());
+    let mut network: Option<Arc<Network>> = None;
 
     loop {
         let mut input = String::new();
@@ -64,80 +203,226 @@ pub fn start_uci() {
         let input = input.trim();
 
         if input == "quit" {
+            stop_search(&signals, &mut search_thread);
             break;
-        } else if input.starts_with("uci") {
+        } else if input == "stop" {
+            stop_search(&signals, &mut search_thread);
+        } else if input == "ponderhit" {
+            signals.ponderhit();
+        } else if input == "uci" {
             writeln!(stdout, "id name Sigma0").expect("Failed to write response");
             writeln!(stdout, "id author T.M Ahad").expect("Failed to write response");
 
-            for (k, opt) in &options {
+            for (k, opt) in options.iter().filter(|(_, opt)| !opt.hidden) {
                 let bind = String::new();
-
                 let default_value = opt.default.as_ref().unwrap_or(&bind);
-                let min_value = opt.min.unwrap_or(i32::MIN);
-                let max_value = opt.max.unwrap_or(i32::MAX);
-                
+
+                // Only spin options have bounds
+                let bounds = match (opt.min, opt.max) {
+                    (Some(min), Some(max)) => format!(" min {} max {}", min, max),
+                    _ => String::new(),
+                };
+
                 writeln!(
                     stdout,
-                    "option name {} type {} default {} min {} max {}",
-                    k, opt.option_type, default_value, min_value, max_value
+                    "option name {} type {} default {}{}",
+                    k, opt.option_type, default_value, bounds
                 ).expect("Failed to write response");
             }
 
             writeln!(stdout, "uciok").expect("Failed to write response");
+        } else if input == "eval" {
+            writeln!(stdout, "{}", trace_eval(&board, history.plies(), &eval_params)).expect("Failed to write response");
+
+            if let Some(network) = &network {
+                writeln!(stdout, "NNUE evaluation: {:+.2} (white side)", nnue_eval(network, &board)).expect("Failed to write response");
+            }
         } else if input == "isready" {
             writeln!(stdout, "readyok").expect("Failed to write response");
         } else if input.starts_with("ucinewgame") {
+            stop_search(&signals, &mut search_thread);
             board = Board::default();
+            history = GameHistory::new(&board, 0, 0);
+            transposition_table.clear();
         } else if input.starts_with("position") {
-            board = Board::default();
-            
-            if input.contains("startpos") {
-                if let Some(moves_index) = input.find("moves") {
-                    let moves_len = "moves".len();
-                    let moves: Vec<&str> = input[moves_index + moves_len..]
-                        .split_whitespace()
-                        .collect();
-
-                    plies = moves.len() as i32;
-                    for mv_str in moves {
-                        let mv = ChessMove::from_str(mv_str)
-                            .unwrap_or_else(|_| panic!("Invalid move"));
-
-                        board = board.make_move_new(mv);
-                    }
+            match parse_position(input) {
+                Ok((position_board, position_history)) => {
+                    board = position_board;
+                    history = position_history;
                 }
-            } else if let Some(fen_index) = input.find("fen") {
-                let fen = &input[fen_index + 4..].trim();
-                board = Board::from_str(fen).unwrap();
+                Err(err) => writeln!(stdout, "info string {}", err).expect("Failed to write response"),
+            }
+        } else if let Some(depth) = input.strip_prefix("go perft") {
+            stop_search(&signals, &mut search_thread);
+
+            match depth.trim().parse::<u8>() {
+                Ok(depth) => print_divide(&board, depth),
+                Err(_) => writeln!(stdout, "info string invalid perft depth {}", depth.trim()).expect("Failed to write response"),
             }
         } else if input.starts_with("go") {
             if board.status() != BoardStatus::Ongoing {
                 _ = writeln!(stdout, "bestmove 0000");
+                continue;
             }
 
-            let best_move = engine(&board, plies, cloned_db.lock().unwrap());
-            
-            writeln!(stdout, "bestmove {}", move_to_string(best_move))
-                .expect("Failed to write response");
+            stop_search(&signals, &mut search_thread);
 
-            if plies <= OPENING_BOOK_MAX_PLIES {
-                let cloned_db = cloned_db.clone();
-                let handle = thread::spawn(move || {
-                    let mut db = cloned_db.lock().unwrap();
-                    db.add_move(&board);
-                });
+            let limits = SearchLimits::from_go_command(input);
+            let search_options = SearchOptions {
+                move_overhead: options["Move Overhead"].int_value() as u64,
+                threads: options["Threads"].int_value() as usize,
+                syzygy: syzygy.clone(),
+                online_tablebase: options["SyzygyOnline"].bool_value(),
+                book_depth: options["BookDepth"].int_value(),
+                contempt: options["Contempt"].int_value(),
+                eval_params: eval_params.clone(),
+                network: network.clone(),
+                extension_policy: ExtensionPolicy {
+                    extension_margin: centipawn_margin(options["SwingExtension"].int_value()),
+                    reduction_margin: centipawn_margin(options["SwingReduction"].int_value()),
+                },
+                pruning: PruningOptions {
+                    null_move: options["NullMovePruning"].bool_value(),
+                    late_move_reductions: options["LateMoveReductions"].bool_value(),
+                    reverse_futility: options["ReverseFutility"].bool_value(),
+                    futility: options["FutilityPruning"].bool_value(),
+                    razoring: options["Razoring"].bool_value(),
+                    late_move_pruning: options["LateMovePruning"].bool_value(),
+                },
+                print_info: true,
+            };
 
-                handle.join().unwrap();
-            }
-        } else if input.starts_with("setoption name") {
-            if let Some((name, value)) = input[14..].split_once(" value ") {
+            // Learning waits for the engine to be idle again
+            learner.pause();
+
+            let learning_depth = (history.plies() <= OPENING_BOOK_MAX_PLIES && options["BookLearning"].bool_value())
+                .then(|| options["BookLearningDepth"].int_value() as u8);
+
+            signals.reset(limits.ponder);
+
+            let cloned_db = cloned_db.clone();
+            let history = history.clone();
+            let transposition_table = transposition_table.clone();
+            let signals = signals.clone();
+            let learner = learner.clone();
+
+            search_thread = Some(thread::spawn(move || {
+                let search_move = engine(
+                    &board,
+                    &history,
+                    &mut *cloned_db.lock().unwrap(),
+                    &transposition_table,
+                    &limits,
+                    &search_options,
+                    signals.clone()
+                );
+
+                let best_move = search_move.mov().unwrap();
+                let ponder_move = search_move.pv().get(1).copied();
+
+                // Only a search deeper than the game's can improve on its move,
+                // book and tablebase moves come without a search to improve on
+                if let Some(depth) = learning_depth.filter(|depth| search_move.depth() > 0 && *depth > search_move.depth()) {
+                    learner.schedule(board, history.plies(), depth, search_options);
+                }
+
+                // The GUI expects no bestmove before it ends an infinite or ponder search
+                while !signals.is_stopped() && (limits.infinite || signals.is_pondering()) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+
+                let mut stdout = io::stdout();
+
+                match ponder_move {
+                    Some(ponder_move) => writeln!(stdout, "bestmove {} ponder {}", move_to_string(best_move), move_to_string(ponder_move)),
+                    None => writeln!(stdout, "bestmove {}", move_to_string(best_move)),
+                }.expect("Failed to write response");
+
+                learner.resume();
+            }));
+        } else if let Some(rest) = input.strip_prefix("setoption name") {
+            if let Some((name, value)) = rest.split_once(" value ") {
                 let name = name.trim();
                 if let Some(option) = options.get_mut(name) {
-                    option.set_value(value.trim());
+                    if let Err(err) = option.set_value(value.trim()) {
+                        writeln!(stdout, "info string {} for {}", err, name).expect("Failed to write response");
+                    } else if name == "Hash" {
+                        stop_search(&signals, &mut search_thread);
+                        transposition_table = Arc::new(TranspostionTable::new(option.int_value() as usize));
+                    } else if name == "SyzygyPath" {
+                        stop_search(&signals, &mut search_thread);
+                        let path = option.string_value();
+
+                        syzygy = if path.is_empty() { None } else {
+                            match SyzygyTablebase::load(&path) {
+                                Ok(tablebase) => {
+                                    writeln!(stdout, "info string Syzygy tablebases found up to {} pieces", tablebase.max_pieces())
+                                        .expect("Failed to write response");
+                                    Some(Arc::new(tablebase))
+                                }
+                                Err(err) => {
+                                    writeln!(stdout, "info string {}", err).expect("Failed to write response");
+                                    None
+                                }
+                            }
+                        };
+                    } else if name == "EvalParamsFile" {
+                        let path = option.string_value();
+
+                        // Individual overrides made so far are replaced by the file
+                        let params = if path.is_empty() { Ok(EvalParams::default()) } else { EvalParams::load(&path) };
+
+                        match params {
+                            Ok(params) => eval_params = Arc::new(params),
+                            Err(err) => writeln!(stdout, "info string {}", err).expect("Failed to write response"),
+                        }
+                    } else if name == "EvalFile" {
+                        let path = option.string_value();
+
+                        // An empty path goes back to the handcrafted evaluation
+                        network = None;
+
+                        if !path.is_empty() {
+                            match Network::load(&path) {
+                                Ok(loaded) => {
+                                    writeln!(stdout, "info string loaded NNUE {} with {} hidden neurons", path, loaded.hidden())
+                                        .expect("Failed to write response");
+                                    network = Some(Arc::new(loaded));
+                                }
+                                Err(err) => writeln!(stdout, "info string {}", err).expect("Failed to write response"),
+                            }
+                        }
+                    } else if name == "BookFile" || name == "BookBestMove" {
+                        stop_search(&signals, &mut search_thread);
+
+                        let path = options["BookFile"].string_value();
+                        let book = if path.is_empty() {
+                            None
+                        } else {
+                            match PolyglotBook::load(&path) {
+                                Ok(book) => {
+                                    writeln!(stdout, "info string loaded {} book entries from {}", book.len(), path)
+                                        .expect("Failed to write response");
+                                    Some(book)
+                                }
+                                Err(err) => {
+                                    writeln!(stdout, "info string book {} can't be loaded: {}", path, err)
+                                        .expect("Failed to write response");
+                                    None
+                                }
+                            }
+                        };
+
+                        cloned_db.lock().unwrap().set_book(book, options["BookBestMove"].bool_value());
+                    }
+                } else if let Some(weight) = name.strip_prefix(EVAL_OPTION_PREFIX) {
+                    if let Err(err) = Arc::make_mut(&mut eval_params).set(weight, value.trim()) {
+                        writeln!(stdout, "info string {}", err).expect("Failed to write response");
+                    }
                 }
             }
-        } else if input.starts_with("getoption name") {
-            let name = input[14..].trim();
+        } else if let Some(rest) = input.strip_prefix("getoption name") {
+            let name = rest.trim();
             if let Some(option) = options.get(name) {
                 writeln!(
                     stdout,
@@ -155,3 +440,31 @@ pub fn start_uci() {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spin_values_are_clamped_and_checked() {
+        let mut option = ExtendedOption::new("spin", Some("64"), Some(1), Some(2048));
+
+        option.set_value("100000").unwrap();
+        assert_eq!(option.int_value(), 2048);
+
+        option.set_value("-5").unwrap();
+        assert_eq!(option.int_value(), 1);
+
+        assert!(option.set_value("lots").is_err());
+        assert_eq!(option.int_value(), 1);
+    }
+
+    #[test]
+    fn other_values_are_kept_as_given() {
+        let mut option = ExtendedOption::new("string", Some("<empty>"), None, None);
+        assert_eq!(option.string_value(), "");
+
+        option.set_value("/tmp/book.bin").unwrap();
+        assert_eq!(option.string_value(), "/tmp/book.bin");
+    }
+}
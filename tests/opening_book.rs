@@ -0,0 +1,82 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const AFTER_E4: &str = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1";
+
+/// Starts the engine with `book` as its `OPENING_BOOK` and no Redis.
+fn sigma0(book: &str) -> Child
+{
+    Command::new(env!("CARGO_BIN_EXE_Sigma0"))
+        .env("OPENING_BOOK", book)
+        .env_remove("REDIS_CONNECTION")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run Sigma0")
+}
+
+fn uci(book: &str, commands: &str) -> String
+{
+    let mut child = sigma0(book);
+    child.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn book_path(name: &str) -> PathBuf
+{
+    std::env::temp_dir().join(format!("sigma0-{}-{}", std::process::id(), name))
+}
+
+#[test]
+fn memory_book_runs_without_redis()
+{
+    let output = uci("memory", "position startpos moves e2e4\ngo depth 2\nquit\n");
+
+    assert!(!output.contains("can't be loaded"));
+    assert!(output.lines().any(|line| line.starts_with("bestmove")));
+}
+
+#[test]
+fn file_book_move_is_played()
+{
+    let path = book_path("played.csv");
+    fs::write(&path, format!("{},a7a6\n", AFTER_E4)).unwrap();
+
+    let output = uci(path.to_str().unwrap(), "position startpos moves e2e4\ngo depth 2\nquit\n");
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.lines().any(|line| line == "bestmove a7a6"), "{}", output);
+}
+
+#[test]
+fn file_book_learns_while_idle()
+{
+    let path = book_path("learned.json");
+    let mut child = sigma0(path.to_str().unwrap());
+    let mut stdin = child.stdin.take().unwrap();
+
+    stdin.write_all(b"setoption name BookLearningDepth value 4\nposition startpos moves e2e4\ngo depth 2\n").unwrap();
+
+    // The learning search starts once the game search has answered
+    let start = Instant::now();
+
+    while !fs::read_to_string(&path).is_ok_and(|content| content.contains(AFTER_E4))
+    {
+        assert!(start.elapsed() < Duration::from_secs(30), "nothing learned");
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    stdin.write_all(b"quit\n").unwrap();
+    drop(stdin);
+
+    assert!(child.wait().unwrap().success());
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,116 @@
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const PERFT_SUITE: &str = include_str!("data/perft.epd");
+
+fn sigma0(arguments: &[&str]) -> Output
+{
+    Command::new(env!("CARGO_BIN_EXE_Sigma0"))
+        .args(arguments)
+        .output()
+        .expect("Failed to run Sigma0")
+}
+
+fn uci(commands: &str) -> String
+{
+    let mut child = Command::new(env!("CARGO_BIN_EXE_Sigma0"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run Sigma0");
+
+    child.stdin.take().unwrap().write_all(commands.as_bytes()).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+fn nodes_searched(output: &str) -> u64
+{
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("Nodes searched: "))
+        .expect("No node count in the output")
+        .parse()
+        .unwrap()
+}
+
+/// Every `;D<depth> <nodes>` of the bundled suite, as `(fen, depth, nodes)`.
+fn suite() -> Vec<(String, u8, u64)>
+{
+    PERFT_SUITE
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .flat_map(|line|
+        {
+            let mut fields = line.split(';');
+            let fen = fields.next().unwrap().trim().to_string();
+
+            fields
+                .map(|field|
+                {
+                    let (depth, nodes) = field.trim().trim_start_matches('D').split_once(' ').unwrap();
+                    (fen.clone(), depth.parse().unwrap(), nodes.parse().unwrap())
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[test]
+fn perft_suite()
+{
+    for (fen, depth, nodes) in suite()
+    {
+        let output = sigma0(&["perft", &fen, &depth.to_string()]);
+        assert!(output.status.success(), "perft failed on {}", fen);
+
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(nodes_searched(&stdout), nodes, "{} at depth {}", fen, depth);
+    }
+}
+
+#[test]
+fn perft_divide_sums_to_total()
+{
+    let output = sigma0(&["perft", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR", "w", "KQkq", "-", "0", "1", "3"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+
+    let divided = stdout
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .filter(|(mov, _)| mov.len() <= 5)
+        .filter_map(|(_, nodes)| nodes.parse::<u64>().ok())
+        .collect::<Vec<u64>>();
+
+    assert_eq!(divided.len(), 20);
+    assert_eq!(divided.iter().sum::<u64>(), nodes_searched(&stdout));
+    assert!(stdout.contains("e2e4: 600"));
+}
+
+#[test]
+fn perft_rejects_bad_input()
+{
+    assert!(!sigma0(&["perft", "not a fen", "3"]).status.success());
+    assert!(!sigma0(&["perft", "8/8/8/8/8/8/8/8 w - - 0 1", "3"]).status.success());
+    assert!(!sigma0(&["perft", "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", "x"]).status.success());
+}
+
+#[test]
+fn go_perft_after_moves()
+{
+    let output = uci("position startpos moves e2e4\ngo perft 2\nquit\n");
+
+    assert_eq!(nodes_searched(&output), 600);
+}
+
+#[test]
+fn bad_position_keeps_previous_one()
+{
+    let output = uci("position startpos moves e2e4\nposition fen invalid\nposition startpos moves e2e4 e2e4\ngo perft 2\nquit\n");
+
+    assert_eq!(output.matches("info string").count(), 2);
+    assert_eq!(nodes_searched(&output), 600);
+}
@@ -0,0 +1,149 @@
+use std::io::{BufRead, BufReader, Lines, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+/// Node total of `Sigma0 bench 3`. Any change to the search or the
+/// evaluation moves it, update it when that is intended.
+const BENCH_NODES: u64 = 17343;
+
+const PRUNING_OPTIONS: [&str; 6] = ["NullMovePruning", "LateMoveReductions", "ReverseFutility", "FutilityPruning", "Razoring", "LateMovePruning"];
+
+/// Mates with the score the search has to report, the mating move where
+/// only one leads there.
+const MATES: [(&str, &str, Option<&str>); 3] =
+[
+    ("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 30", "mate 2", Some("d5d8")),
+    ("7k/8/8/4K3/8/8/8/6Q1 w - - 0 30", "mate 2", None),
+    ("2k5/8/8/3K4/8/8/8/7R w - - 0 30", "mate 3", None)
+];
+
+/// An engine driven one command at a time, without an opening book.
+struct Engine
+{
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>
+}
+
+impl Engine
+{
+    fn start() -> Engine
+    {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_Sigma0"))
+            .env("OPENING_BOOK", "memory")
+            .env_remove("REDIS_CONNECTION")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .expect("Failed to run Sigma0");
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = BufReader::new(child.stdout.take().unwrap()).lines();
+
+        Engine { child, stdin, stdout }
+    }
+
+    fn send(&mut self, command: &str)
+    {
+        writeln!(self.stdin, "{}", command).unwrap();
+    }
+
+    /// Searches `fen` to `depth`, returns the last `info depth` line and the
+    /// best move.
+    fn search(&mut self, fen: &str, depth: u8) -> (String, String)
+    {
+        self.send(&format!("position fen {}", fen));
+        self.send(&format!("go depth {}", depth));
+
+        let mut info = String::new();
+
+        for line in self.stdout.by_ref().map(Result::unwrap)
+        {
+            if let Some(best_move) = line.strip_prefix("bestmove ")
+            {
+                let best_move = best_move.split_whitespace().next().unwrap().to_string();
+                return (info, best_move);
+            }
+
+            if line.starts_with("info depth")
+            {
+                info = line;
+            }
+        }
+
+        panic!("Sigma0 exited before answering");
+    }
+
+    fn quit(mut self)
+    {
+        self.send("quit");
+        assert!(self.child.wait().unwrap().success());
+    }
+}
+
+fn assert_mates(engine: &mut Engine, setting: &str)
+{
+    for (fen, score, mating_move) in MATES
+    {
+        let (info, best_move) = engine.search(fen, 8);
+
+        assert!(info.contains(&format!("score {} ", score)), "{} with {}: {}", fen, setting, info);
+
+        if let Some(mating_move) = mating_move
+        {
+            assert_eq!(best_move, mating_move, "{} with {}", fen, setting);
+        }
+    }
+}
+
+#[test]
+fn mates_are_found_with_each_pruning_option_on_and_off()
+{
+    let mut engine = Engine::start();
+    assert_mates(&mut engine, "every option on");
+
+    for option in PRUNING_OPTIONS
+    {
+        engine.send(&format!("setoption name {} value false", option));
+        assert_mates(&mut engine, &format!("{} off", option));
+        engine.send(&format!("setoption name {} value true", option));
+    }
+
+    for option in PRUNING_OPTIONS
+    {
+        engine.send(&format!("setoption name {} value false", option));
+    }
+
+    assert_mates(&mut engine, "every option off");
+    engine.quit();
+}
+
+/// White wins with 1.Kh6, after which Black has to give ground. A null move
+/// cutoff taken on trust misses it at this depth.
+#[test]
+fn null_move_verification_finds_zugzwang()
+{
+    let mut engine = Engine::start();
+    let (_, best_move) = engine.search("1q1k4/2Rr4/8/2Q3K1/8/8/8/8 w - - 0 30", 10);
+
+    assert_eq!(best_move, "g5h6");
+    engine.quit();
+}
+
+#[test]
+fn bench_node_count()
+{
+    let output = Command::new(env!("CARGO_BIN_EXE_Sigma0"))
+        .args(["bench", "3"])
+        .output()
+        .expect("Failed to run Sigma0");
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let nodes = stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("Nodes searched  : "))
+        .expect("No node count in the output")
+        .parse::<u64>()
+        .unwrap();
+
+    assert_eq!(nodes, BENCH_NODES);
+}